@@ -26,10 +26,10 @@ cargo build --target wasm32-unknown-unknown --release
 # Check if build succeeded
 if [ $? -eq 0 ]; then
     echo "✅ Contract built successfully!"
-    echo "📁 WASM file location: target/wasm32-unknown-unknown/release/stellar_custody_contract.wasm"
+    echo "📁 WASM file location: ../../target/wasm32-unknown-unknown/release/stellar_custody_contract.wasm"
     
     # Display file size
-    WASM_FILE="target/wasm32-unknown-unknown/release/stellar_custody_contract.wasm"
+    WASM_FILE="../../target/wasm32-unknown-unknown/release/stellar_custody_contract.wasm"
     if [ -f "$WASM_FILE" ]; then
         FILE_SIZE=$(du -h "$WASM_FILE" | cut -f1)
         echo "📊 Contract size: $FILE_SIZE"
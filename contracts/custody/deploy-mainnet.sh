@@ -20,7 +20,7 @@ echo ""
 
 # Configuration
 NETWORK="mainnet"
-WASM_FILE="target/wasm32-unknown-unknown/release/stellar_custody_contract.wasm"
+WASM_FILE="../../target/wasm32-unknown-unknown/release/stellar_custody_contract.wasm"
 
 # Check if WASM file exists
 if [ ! -f "$WASM_FILE" ]; then
@@ -97,6 +97,8 @@ CONTRACT_ADDRESS=$(stellar contract deploy \
     --source mainnet_deployer \
     --network "$NETWORK" \
     --fee 1000000 \
+    -- \
+    --admin "$(stellar keys address mainnet_deployer)" \
     2>&1)
 
 # Check deployment result
@@ -5,7 +5,7 @@ echo "🌐 Deploying Stellar Custody Contract to Testnet..."
 
 # Configuration
 NETWORK="testnet"
-WASM_FILE="target/wasm32-unknown-unknown/release/stellar_custody_contract.wasm"
+WASM_FILE="../../target/wasm32-unknown-unknown/release/stellar_custody_contract.wasm"
 
 # Check if WASM file exists
 if [ ! -f "$WASM_FILE" ]; then
@@ -38,6 +38,8 @@ CONTRACT_ADDRESS=$(stellar contract deploy \
     --source deployer \
     --network "$NETWORK" \
     --network-passphrase "Test SDF Network ; September 2015" \
+    -- \
+    --admin "$(stellar keys address deployer)" \
     2>&1)
 
 # Check deployment result
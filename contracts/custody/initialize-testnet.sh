@@ -50,7 +50,7 @@ INIT_RESULT=$(stellar contract invoke \
     --network testnet \
     --network-passphrase "Test SDF Network ; September 2015" \
     -- \
-    initialize \
+    create_vault \
     --guardians "[
         {
             \"address\": \"$GUARDIAN_CEO\",
@@ -92,13 +92,15 @@ INIT_RESULT=$(stellar contract invoke \
     }" \
     2>&1)
 
-if [[ $INIT_RESULT == *"success"* ]] || [[ $INIT_RESULT == *"null"* ]]; then
-    echo "✅ Contract initialized successfully!"
+if [[ $INIT_RESULT =~ ^[0-9]+$ ]]; then
+    VAULT_ID=$INIT_RESULT
+    echo "✅ Vault $VAULT_ID created successfully!"
     
     # Save configuration to file
     cat > contract-config-testnet.json << EOF
 {
     "contract_address": "$CONTRACT_ADDRESS",
+    "vault_id": $VAULT_ID,
     "network": "testnet",
     "guardians": {
         "ceo": "$GUARDIAN_CEO",
@@ -130,8 +132,8 @@ EOF
     echo "4. Test wallet rebalancing"
     echo ""
     echo "📚 Example commands:"
-    echo "stellar contract invoke --id $CONTRACT_ADDRESS --source guardian1 --network testnet --network-passphrase \"Test SDF Network ; September 2015\" -- get_hot_balance"
-    echo "stellar contract invoke --id $CONTRACT_ADDRESS --source guardian1 --network testnet --network-passphrase \"Test SDF Network ; September 2015\" -- get_cold_balance"
+    echo "stellar contract invoke --id $CONTRACT_ADDRESS --source guardian1 --network testnet --network-passphrase \"Test SDF Network ; September 2015\" -- get_hot_balance --vault_id $VAULT_ID"
+    echo "stellar contract invoke --id $CONTRACT_ADDRESS --source guardian1 --network testnet --network-passphrase \"Test SDF Network ; September 2015\" -- get_cold_balance --vault_id $VAULT_ID"
     
 else
     echo "❌ Initialization failed!"
@@ -1,8 +1,9 @@
 #![no_std]
 
 use soroban_sdk::{
-    contract, contractimpl, contracttype, contractmeta, log,
-    Address, Env, Vec, Map, Symbol,
+    auth::{Context, ContractContext, CustomAccountInterface}, contract, contractclient, contracterror, contractevent,
+    contractimpl, contracttype, contractmeta, crypto::Hash, log, unwrap::UnwrapOptimized, xdr::ToXdr,
+    Address, Bytes, BytesN, Env, IntoVal, Vec, Map, String, Symbol, TryFromVal, Val,
 };
 
 // Contract metadata
@@ -30,6 +31,17 @@ pub struct Guardian {
     pub last_approval: u64,
 }
 
+/// Stellar payment memo, forwarded so exchange deposits can be reconciled
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum Memo {
+    None,
+    Text(String), // up to 28 bytes
+    Id(u64),
+    Hash(BytesN<32>),
+    Return(BytesN<32>), // hash of the transaction being refunded
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[contracttype]
 pub struct Transaction {
@@ -37,15 +49,250 @@ pub struct Transaction {
     pub from_wallet: Address,
     pub to_address: Address,
     pub amount: i128,
-    pub memo: Symbol,
+    pub memo: Memo,
     pub tx_type: TxType,
     pub status: TxStatus,
-    pub approvals: Vec<Address>,
+    pub approvals: Vec<Approval>,
+    pub approval_weight: u32, // vote weight tally of `approvals`
+    pub value: Option<i128>,  // reference currency value at creation, None when unpriced
+    pub fee: i128,            // custody fee, reserved at creation and charged on execution
+    pub idempotency_key: Option<BytesN<32>>,
     pub created_at: u64,
     pub executed_at: Option<u64>,
     pub requires_approval: bool,
 }
 
+/// A guardian's vote on a transaction, cast directly or by their delegate
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Approval {
+    pub guardian: Address,
+    pub delegate: Option<Address>, // delegation marker, set when a delegate approved
+}
+
+/// Guardian vote weights and the weight each amount tier needs
+/// (unset: one vote each, threshold `required_approvals`)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct VotingPolicy {
+    pub weights: Vec<GuardianWeight>, // guardians not listed weigh 1
+    pub tiers: Vec<WeightTier>,       // ascending by min_amount, first starts at 0
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct GuardianWeight {
+    pub guardian: Address,
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct WeightTier {
+    pub min_amount: i128,
+    pub required_weight: u32,
+}
+
+/// Custody fees billed to the vault and collected for the custodian
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct FeeSchedule {
+    pub fee_wallet: Address,
+    pub flat_fee: i128,   // per executed transaction
+    pub fee_bps: u32,     // of the transaction amount
+    pub aum_fee_bps: u32, // per year, of the assets under custody
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct FeeState {
+    pub schedule: FeeSchedule,
+    pub last_aum_collection: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct FeeLedgerEntry {
+    pub transaction_fees: i128,
+    pub aum_fees: i128,
+}
+
+/// SEP-40 price feed used to express limits in a reference currency (USD)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct OracleConfig {
+    pub oracle: Address,
+    pub asset: Asset,  // the vault's asset as known to the oracle
+    pub max_age: u64,  // seconds after which a price is stale
+}
+
+/// Reference currency pricing with the limits in that currency
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ReferencePricing {
+    pub oracle: OracleConfig,
+    pub limits: SystemLimits,
+}
+
+/// SEP-40 asset identifier
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum Asset {
+    Stellar(Address),
+    Other(Symbol),
+}
+
+/// SEP-40 price record
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PriceData {
+    pub price: i128,
+    pub timestamp: u64,
+}
+
+/// Velocity caps on what a single destination can receive
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct DestinationLimit {
+    pub daily_amount: i128,   // in the reference currency when an oracle is configured
+    pub monthly_amount: i128,
+    pub daily_count: u32,
+    pub monthly_count: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct DestinationLimitUpdate {
+    pub destination: Option<Address>, // None: default for all destinations
+    pub limit: DestinationLimit,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct DestinationUsage {
+    pub amount: i128,
+    pub count: u32,
+}
+
+/// Backup signer allowed to approve on a guardian's behalf
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Delegation {
+    pub delegate: Address,
+    pub public_key: BytesN<32>, // delegate's ed25519 key for challenge responses
+    pub expires_at: u64,
+    pub max_amount: i128, // largest transaction amount the delegate may approve
+}
+
+/// A single payment submitted with an idempotency key
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PaymentRequest {
+    pub from_wallet: Address,
+    pub to_address: Address,
+    pub amount: i128,
+    pub memo: Memo,
+    pub tx_type: TxType,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct BatchLeg {
+    pub to_address: Address,
+    pub amount: i128,
+    pub memo: Memo,
+    pub status: TxStatus,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ApprovalResult {
+    pub tx_id: u64,
+    pub outcome: ApprovalOutcome,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct GuardianSignature {
+    pub guardian: Address,
+    pub signature: BytesN<64>,
+}
+
+/// `__check_auth` signature of a vault's guardians
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct AccountSignature {
+    pub vault_id: u32,
+    pub signatures: Vec<GuardianSignature>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PaymentSchedule {
+    pub id: u64,
+    pub from_wallet: Address,
+    pub to_address: Address,
+    pub amount: i128,
+    pub memo: Memo,
+    pub recurrence: Recurrence,
+    pub next_due: u64,
+    pub executed_count: u32,
+    pub approvals: Vec<Address>,
+    pub status: ScheduleStatus,
+    pub created_at: u64,
+}
+
+/// The payment repeated by a schedule
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ScheduledPayment {
+    pub from_wallet: Address,
+    pub to_address: Address,
+    pub amount: i128,
+    pub memo: Memo,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Recurrence {
+    pub interval: u64,
+    pub start_at: u64,
+    pub end: ScheduleEnd,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct EphemeralAccount {
+    pub address: Address,
+    pub tx_id: u64,
+    pub derivation_index: u32, // N in m/0'/0'/N'
+    pub fee_buffer: i128,
+    pub funded_amount: i128,
+    pub swept_amount: i128,
+    pub status: EphemeralStatus,
+    pub expires_at: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct TxSummary {
+    pub id: u64,
+    pub from_wallet: Address,
+    pub to_address: Option<Address>, // None for a batch, destinations are in its legs
+    pub amount: i128,
+    pub tx_type: TxType,
+    pub status: TxStatus,
+    pub approval_count: u32,
+    pub created_at: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct TxPage {
+    pub items: Vec<TxSummary>,
+    pub next_cursor: Option<u32>,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[contracttype]
 pub struct WalletInfo {
@@ -56,6 +303,134 @@ pub struct WalletInfo {
     pub is_active: bool,
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct WalletPolicy {
+    pub daily_limit: i128,
+    pub monthly_limit: i128,
+    pub high_value_threshold: i128,
+    pub required_approvals: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct NamedWallet {
+    pub name: Symbol,
+    pub address: Address,
+    pub wallet_type: WalletType,
+    pub policy: WalletPolicy,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct WalletView {
+    pub info: WalletInfo,
+    pub available_balance: i128, // balance - reserved_balance
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ContractConfig {
+    pub system_limits: SystemLimits,
+    pub hot_wallet: WalletView,
+    pub cold_wallet: WalletView,
+    pub guardian_count: u32,
+    pub active_guardian_count: u32,
+    pub emergency_mode: bool,
+    pub emergency_initiator: Option<Address>,
+    pub compliance_officer: Option<Address>,
+    pub transaction_counter: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PeriodSpending {
+    pub period_key: u64,
+    pub spent: i128,
+    pub limit: i128,
+    pub remaining: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct WalletSpending {
+    pub wallet: Address,
+    pub daily_spent: i128,
+    pub monthly_spent: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct SpendingStatus {
+    pub daily: PeriodSpending,
+    pub monthly: PeriodSpending,
+    pub wallets: Vec<WalletSpending>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: Address,
+    pub action: ProposalAction,
+    pub approvals: Vec<Address>,
+    pub required_approvals: u32,
+    pub status: ProposalStatus,
+    pub created_at: u64,
+    pub expires_at: u64, // approvals are refused after this time
+    pub executed_at: Option<u64>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct LimitsRecord {
+    pub limits: SystemLimits,
+    pub replaced_at: u64,
+    pub proposal_id: u64,
+}
+
+/// Recovery guardians able to replace the guardian set after a timelock
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct RecoveryConfig {
+    pub guardians: Vec<Address>,
+    pub threshold: u32,
+    pub timelock: u64, // seconds between reaching the threshold and execution
+}
+
+/// Dead-man switch: after `inactivity_period` without guardian activity the
+/// beneficiary can claim the cold wallet funds once `timelock` has passed
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct InheritanceConfig {
+    pub beneficiary: Address,
+    pub inactivity_period: u64,
+    pub timelock: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct InheritanceClaim {
+    pub beneficiary: Address,
+    pub amount: i128, // reserved on the cold wallet until executed or cancelled
+    pub status: InheritanceStatus,
+    pub initiated_at: u64,
+    pub executable_at: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct RecoveryRequest {
+    pub id: u64,
+    pub initiator: Address,
+    pub new_guardians: Vec<Guardian>,
+    pub approvals: Vec<Address>,
+    pub status: RecoveryStatus,
+    pub initiated_at: u64,
+    pub executable_at: Option<u64>, // set once the recovery threshold is reached
+    pub cancelled_by: Option<Address>,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[contracttype]
 pub struct SystemLimits {
@@ -72,6 +447,7 @@ pub struct SystemLimits {
 pub enum WalletType {
     Hot,
     Cold,
+    Fee, // custodian's fee wallet, credited with collected fees
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -81,6 +457,7 @@ pub enum TxType {
     Rebalance,
     Withdrawal,
     Emergency,
+    Batch,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -94,67 +471,452 @@ pub enum TxStatus {
     Cancelled,
 }
 
-// ==================== STORAGE KEYS ====================
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum ApprovalOutcome {
+    Recorded,
+    Executed,
+    NotFound,
+    AlreadyApproved,
+    NotAwaitingApproval,
+    InvalidResponse,
+    ExecutionFailed(ExecutionFailure), // quorum reached but execution refused, approval not recorded
+}
 
-#[derive(Clone)]
+/// Why an approved transaction could not execute
+#[derive(Clone, Debug, Eq, PartialEq)]
 #[contracttype]
-pub enum DataKey {
-    // System configuration
-    Initialized,
-    SystemLimits,
-    TransactionCounter,
-    
-    // Guardians
-    Guardians,
-    GuardianCount,
-    
-    // Wallets
-    HotWallet,
-    ColdWallet,
-    WalletInfo(Address),
-    
-    // Transactions
-    Transaction(u64),
-    
-    // Spending tracking
-    DailySpent(u64), // date as key
-    MonthlySpent(u64), // year-month as key
-    
-    // Emergency
+pub enum ExecutionFailure {
     EmergencyMode,
-    EmergencyInitiator,
+    OperationPaused,
+    Denylisted,
+    InsufficientBalance,
+    DestinationLimit,
 }
 
-// ==================== MAIN CONTRACT ====================
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum ScheduleEnd {
+    Never,
+    At(u64),           // no installment due after this timestamp
+    AfterCount(u32),   // total number of installments
+}
 
-#[contract]
-pub struct CustodyContract;
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum ScheduleStatus {
+    AwaitingApproval,
+    Active,
+    Completed,
+    Cancelled,
+}
 
-#[contractimpl]
-impl CustodyContract {
-    
-    /// Initialize the contract with 3 guardians and wallet addresses
-    pub fn initialize(
-        env: Env,
-        guardians: Vec<Guardian>,
-        hot_wallet: Address,
-        cold_wallet: Address,
-        system_limits: SystemLimits,
-    ) {
-        // Check if already initialized
-        if env.storage().instance().has(&DataKey::Initialized) {
-            panic!("Contract already initialized");
-        }
-        
-        // Validate we have exactly 3 guardians
-        if guardians.len() != 3 {
-            panic!("Must have exactly 3 guardians");
-        }
-        
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum EphemeralStatus {
+    Registered,
+    Funded,
+    Swept,
+    Expired,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum TxFilter {
+    All,
+    Status(TxStatus),
+    Wallet(Address),
+    Destination(Address),
+    Guardian(Address),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum PeriodKey {
+    Day(u64),   // timestamp / 86400
+    Month(u64), // day / 30
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum ProposalAction {
+    UpdateLimits(SystemLimits),
+    Unpause(Operation),
+    CreateWallet(NamedWallet),
+    RetireWallet(Symbol),
+    SetRecoveryConfig(RecoveryConfig),
+    SetInheritance(InheritanceConfig),
+    SetVotingPolicy(VotingPolicy),
+    SetDestinationLimit(DestinationLimitUpdate),
+    RemoveDestinationLimit(Option<Address>),
+    SetReferencePricing(ReferencePricing),
+    SetFeeSchedule(FeeSchedule),
+    SetComplianceOfficer(Address),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum Operation {
+    Payments,
+    Withdrawals,
+    Rebalances,
+    Deposits,
+    ColdWallet,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum ProposalStatus {
+    Pending,
+    Executed,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum RecoveryStatus {
+    Pending,
+    Executed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum InheritanceStatus {
+    Pending,
+    Executed,
+    Cancelled,
+}
+
+// ==================== EVENTS ====================
+
+/// A payment left the vault, with the memo needed to credit exchange deposits
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TransactionExecuted {
+    #[topic]
+    pub vault_id: u32,
+    pub tx_id: u64,
+    pub from_wallet: Address,
+    pub to_address: Address,
+    pub amount: i128,
+    pub memo: Memo,
+}
+
+/// Notice that a guardian recovery was started for a vault
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryInitiated {
+    #[topic]
+    pub vault_id: u32,
+    pub recovery_id: u64,
+    pub initiator: Address,
+}
+
+/// A recovery reached its threshold
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryScheduled {
+    #[topic]
+    pub vault_id: u32,
+    pub recovery_id: u64,
+    pub executable_at: u64,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryCancelled {
+    #[topic]
+    pub vault_id: u32,
+    pub recovery_id: u64,
+    pub guardian: Address,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryExecuted {
+    #[topic]
+    pub vault_id: u32,
+    pub recovery_id: u64,
+}
+
+/// The beneficiary started an inheritance claim
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InheritanceInitiated {
+    #[topic]
+    pub vault_id: u32,
+    pub beneficiary: Address,
+    pub amount: i128,
+    pub executable_at: u64,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InheritanceCancelled {
+    #[topic]
+    pub vault_id: u32,
+    pub guardian: Address,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InheritanceExecuted {
+    #[topic]
+    pub vault_id: u32,
+    pub beneficiary: Address,
+    pub amount: i128,
+}
+
+// ==================== ERRORS ====================
+
+/// Custom account errors returned by `__check_auth`
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum AccountError {
+    NotInitialized = 1,
+    EmergencyMode = 2,
+    NotAGuardian = 3,
+    GuardianNotActive = 4,
+    KeyNotRegistered = 5,
+    DuplicateSigner = 6,
+    InsufficientSignatures = 7,
+    VaultMismatch = 8,
+    OperationPaused = 9,
+    Denylisted = 10,
+    LimitExceeded = 11,
+    UnsupportedContext = 12,
+    InsufficientBalance = 13,
+}
+
+// ==================== ORACLE ====================
+
+/// SEP-40 price oracle interface (subset)
+#[contractclient(name = "PriceOracleClient")]
+pub trait PriceOracle {
+    fn lastprice(env: Env, asset: Asset) -> Option<PriceData>;
+    fn decimals(env: Env) -> u32;
+}
+
+// ==================== STORAGE KEYS ====================
+
+// Deployment-wide keys (instance storage)
+#[derive(Clone)]
+#[contracttype]
+pub enum GlobalKey {
+    Admin,
+    VaultCounter,
+}
+
+// Per-vault fee keys, stored as `(vault_id, FeeKey)` in persistent storage
+// (kept apart from `DataKey`, whose 48 variants are close to the contract spec's 50 variant limit)
+#[derive(Clone)]
+#[contracttype]
+pub enum FeeKey {
+    State,
+    Ledger(PeriodKey),
+}
+
+// Per-vault transaction index keys, stored as `(vault_id, IndexKey)` in persistent storage.
+// Each index is split into pages of `INDEX_PAGE_SIZE` ids.
+#[derive(Clone)]
+#[contracttype]
+pub enum IndexKey {
+    Len(TxFilter),
+    Page(TxFilter, u32),
+    StatusSlot(u64), // position of a transaction in its status index
+}
+
+// Per-vault keys, always stored as `(vault_id, DataKey)` in persistent storage
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    // System configuration
+    Initialized,
+    SystemLimits,
+    TransactionCounter,
+    OracleConfig,
+    
+    // Guardians
+    Guardians,
+    GuardianCount,
+    GuardianKey(Address),   // ed25519 public key for off-chain approvals
+    Delegation(Address),    // by delegating guardian
+    VotingPolicy,
+    
+    // Wallets
+    HotWallet,
+    ColdWallet,
+    WalletInfo(Address),
+    
+    // Named sub-accounts
+    WalletNames,
+    NamedWallet(Symbol),
+    WalletPolicy(Address),
+    
+    // Transactions
+    Transaction(u64),
+    BatchLegs(u64),
+    SignatureNonce(u64),
+    IdempotencyKey(BytesN<32>), // tx id created with the key
+    Challenge(u64),     // OCRA-style challenge commitment
+    
+    // Scheduled payments
+    ScheduleCounter,
+    Schedule(u64),
+    
+    // Spending tracking
+    DailySpent(u64), // date as key
+    MonthlySpent(u64), // year-month as key
+    WalletDailySpent(Address, u64),
+    WalletMonthlySpent(Address, u64),
+    
+    // Per-destination velocity
+    DefaultDestinationLimit,
+    DestinationLimit(Address),
+    DestinationDaily(Address, u64),
+    DestinationMonthly(Address, u64),
+    
+    // Emergency
+    EmergencyMode,
+    EmergencyInitiator,
+    Paused(Operation),
+    
+    // Governance
+    ProposalCounter,
+    Proposal(u64),
+    LimitsHistory,
+    
+    // Guardian recovery
+    RecoveryConfig,
+    RecoveryCounter,
+    Recovery(u64),
+    PendingRecovery,
+    
+    // Dead-man switch
+    InheritanceConfig,
+    InheritanceClaim,
+    LastHeartbeat,
+    
+    // Ephemeral per-transaction source accounts
+    Ephemeral(u64), // persistent storage, by tx id
+    EphemeralAddress(Address), // tx id the address was registered for
+    DerivationIndex(u32),      // tx id the index was registered for
+    
+    // Compliance
+    ComplianceOfficer,
+    Denylisted(Address), // persistent storage
+}
+
+/// Parameters of a transaction about to be created
+struct NewTransaction {
+    from_wallet: Address,
+    to_address: Address,
+    amount: i128,
+    memo: Memo,
+    tx_type: TxType,
+    legs: Option<Vec<BatchLeg>>,
+    idempotency_key: Option<BytesN<32>>,
+}
+
+// ==================== CONSTANTS ====================
+
+const MAX_BATCH_LEGS: u32 = 100;
+const MAX_PAGE_SIZE: u32 = 50;
+const INDEX_PAGE_SIZE: u32 = 50;
+const MAX_MEMO_TEXT_LEN: u32 = 28; // Stellar MEMO_TEXT limit in bytes
+const BPS_DENOMINATOR: i128 = 10000;
+const CHALLENGE_DOMAIN: &[u8] = b"stellar-custody:challenge";
+const SECONDS_PER_YEAR: i128 = 365 * 86400;
+const PROPOSAL_LIFETIME: u64 = 7 * 86400; // 7 days
+const MIN_RECOVERY_TIMELOCK: u64 = 7 * 86400; // 7 days
+const MIN_INACTIVITY_PERIOD: u64 = 30 * 86400; // 30 days
+const MIN_INHERITANCE_TIMELOCK: u64 = 7 * 86400; // 7 days
+const DAY_IN_LEDGERS: u32 = 17280; // ~5 seconds per ledger
+const TTL_EXTEND_TO: u32 = 120 * DAY_IN_LEDGERS;
+const TTL_THRESHOLD: u32 = TTL_EXTEND_TO - 30 * DAY_IN_LEDGERS;
+
+// ==================== STORAGE ====================
+
+/// Persistent storage access that keeps vault state from being archived:
+/// every read and write extends the entry's TTL
+trait VaultStorage {
+    fn load<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(&self, key: &K) -> Option<V>;
+    fn save<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(&self, key: &K, value: &V);
+    fn exists<K: IntoVal<Env, Val>>(&self, key: &K) -> bool;
+}
+
+impl VaultStorage for Env {
+    fn load<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(&self, key: &K) -> Option<V> {
+        load_val(self, key.into_val(self)).map(|value| V::try_from_val(self, &value).unwrap_optimized())
+    }
+    
+    fn save<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(&self, key: &K, value: &V) {
+        save_val(self, key.into_val(self), value.into_val(self));
+    }
+    
+    fn exists<K: IntoVal<Env, Val>>(&self, key: &K) -> bool {
+        exists_val(self, key.into_val(self))
+    }
+}
+
+// Non-generic storage access, so each key/value type only adds its conversions
+fn load_val(env: &Env, key: Val) -> Option<Val> {
+    let value: Option<Val> = env.storage().persistent().get(&key);
+    if value.is_some() {
+        env.storage().persistent().extend_ttl(&key, TTL_THRESHOLD, TTL_EXTEND_TO);
+    }
+    value
+}
+
+fn save_val(env: &Env, key: Val, value: Val) {
+    env.storage().persistent().set(&key, &value);
+    env.storage().persistent().extend_ttl(&key, TTL_THRESHOLD, TTL_EXTEND_TO);
+}
+
+fn exists_val(env: &Env, key: Val) -> bool {
+    let exists = env.storage().persistent().has(&key);
+    if exists {
+        env.storage().persistent().extend_ttl(&key, TTL_THRESHOLD, TTL_EXTEND_TO);
+    }
+    exists
+}
+
+// ==================== MAIN CONTRACT ====================
+
+#[contract]
+pub struct CustodyContract;
+
+#[contractimpl]
+impl CustodyContract {
+    
+    /// Set the deployment admin allowed to create vaults
+    pub fn __constructor(env: Env, admin: Address) {
+        env.storage().instance().set(&GlobalKey::Admin, &admin);
+    }
+    
+    /// Create a vault with 3 guardians and wallet addresses (deployment admin)
+    pub fn create_vault(
+        env: Env,
+        guardians: Vec<Guardian>,
+        hot_wallet: Address,
+        cold_wallet: Address,
+        system_limits: SystemLimits,
+    ) -> u32 {
+        let admin = Self::get_admin(env.clone());
+        admin.require_auth();
+        
+        // Validate we have exactly 3 distinct guardians
+        Self::validate_guardian_set(&guardians);
+        
         // Validate system limits
-        if system_limits.cold_wallet_percentage + system_limits.hot_wallet_percentage != 100 {
-            panic!("Wallet percentages must equal 100%");
-        }
+        Self::validate_limits(&system_limits, guardians.len());
+        
+        // Allocate the vault id
+        let counter: u32 = env.storage().instance().get(&GlobalKey::VaultCounter).unwrap_or(0);
+        let vault_id = counter + 1;
+        env.storage().instance().set(&GlobalKey::VaultCounter, &vault_id);
+        env.storage().instance().extend_ttl(TTL_THRESHOLD, TTL_EXTEND_TO);
         
         // Store guardians
         let mut guardians_map: Map<Address, Guardian> = Map::new(&env);
@@ -163,14 +925,15 @@ impl CustodyContract {
         }
         
         // Initialize storage
-        env.storage().instance().set(&DataKey::Guardians, &guardians_map);
-        env.storage().instance().set(&DataKey::GuardianCount, &3u32);
-        env.storage().instance().set(&DataKey::HotWallet, &hot_wallet);
-        env.storage().instance().set(&DataKey::ColdWallet, &cold_wallet);
-        env.storage().instance().set(&DataKey::SystemLimits, &system_limits);
-        env.storage().instance().set(&DataKey::TransactionCounter, &0u64);
-        env.storage().instance().set(&DataKey::EmergencyMode, &false);
-        env.storage().instance().set(&DataKey::Initialized, &true);
+        env.save(&(vault_id, DataKey::Guardians), &guardians_map);
+        env.save(&(vault_id, DataKey::GuardianCount), &3u32);
+        env.save(&(vault_id, DataKey::HotWallet), &hot_wallet);
+        env.save(&(vault_id, DataKey::ColdWallet), &cold_wallet);
+        env.save(&(vault_id, DataKey::SystemLimits), &system_limits);
+        env.save(&(vault_id, DataKey::TransactionCounter), &0u64);
+        env.save(&(vault_id, DataKey::EmergencyMode), &false);
+        env.save(&(vault_id, DataKey::Initialized), &true);
+        env.save(&(vault_id, DataKey::LastHeartbeat), &env.ledger().timestamp());
         
         // Initialize wallet info
         let hot_wallet_info = WalletInfo {
@@ -189,462 +952,4525 @@ impl CustodyContract {
             is_active: true,
         };
         
-        env.storage().instance().set(&DataKey::WalletInfo(hot_wallet), &hot_wallet_info);
-        env.storage().instance().set(&DataKey::WalletInfo(cold_wallet), &cold_wallet_info);
+        env.save(&(vault_id, DataKey::WalletInfo(hot_wallet)), &hot_wallet_info);
+        env.save(&(vault_id, DataKey::WalletInfo(cold_wallet)), &cold_wallet_info);
         
-        log!(&env, "Custody contract initialized with 3 guardians");
+        log!(&env, "Vault {} created with 3 guardians", vault_id);
+        vault_id
     }
     
     /// Create a new transaction
     pub fn create_transaction(
         env: Env,
+        vault_id: u32,
         from_wallet: Address,
         to_address: Address,
         amount: i128,
-        memo: Symbol,
+        memo: Memo,
         tx_type: TxType,
     ) -> u64 {
-        Self::check_initialized(&env);
-        Self::check_emergency_mode(&env);
-        
-        // Validate amount
-        if amount <= 0 {
-            panic!("Amount must be positive");
-        }
-        
-        // Get wallet info and check balance
-        let wallet_key = DataKey::WalletInfo(from_wallet.clone());
-        let mut wallet_info: WalletInfo = env.storage().instance()
-            .get(&wallet_key)
-            .unwrap_or_else(|| panic!("Wallet not found"));
-            
-        if wallet_info.balance < amount {
-            panic!("Insufficient balance");
-        }
-        
-        // Check if requires approval
-        let system_limits: SystemLimits = env.storage().instance().get(&DataKey::SystemLimits).unwrap();
-        let requires_approval = amount > system_limits.high_value_threshold || 
-                               matches!(wallet_info.wallet_type, WalletType::Cold);
-        
-        // Check spending limits if requires approval
-        if requires_approval {
-            Self::check_spending_limits(&env, amount);
+        if matches!(tx_type, TxType::Batch) {
+            panic!("Use create_batch_transaction for batches");
         }
         
-        // Get transaction counter and increment
-        let counter: u64 = env.storage().instance().get(&DataKey::TransactionCounter).unwrap_or(0);
-        let tx_id = counter + 1;
-        
-        // Create transaction
-        let transaction = Transaction {
-            id: tx_id,
-            from_wallet: from_wallet.clone(),
+        Self::create_transaction_internal(&env, vault_id, NewTransaction {
+            from_wallet,
             to_address,
             amount,
             memo,
             tx_type,
-            status: if requires_approval { TxStatus::AwaitingApproval } else { TxStatus::Pending },
-            approvals: Vec::new(&env),
-            created_at: env.ledger().timestamp(),
-            executed_at: None,
-            requires_approval,
-        };
+            legs: None,
+            idempotency_key: None,
+        })
+    }
+    
+    /// Create a transaction at most once per idempotency key; a repeated key
+    /// returns the existing transaction id
+    pub fn create_transaction_idempotent(
+        env: Env,
+        vault_id: u32,
+        idempotency_key: BytesN<32>,
+        request: PaymentRequest,
+    ) -> u64 {
+        // Only the paying wallet may create or look up its payments
+        request.from_wallet.require_auth();
         
-        // Reserve balance
-        wallet_info.reserved_balance += amount;
-        env.storage().instance().set(&wallet_key, &wallet_info);
+        let existing: Option<u64> = env.load(&(vault_id, DataKey::IdempotencyKey(idempotency_key.clone())));
+        if let Some(tx_id) = existing {
+            let transaction: Transaction = env.load(&(vault_id, DataKey::Transaction(tx_id))).unwrap();
+            if transaction.from_wallet != request.from_wallet ||
+               transaction.to_address != request.to_address ||
+               transaction.amount != request.amount ||
+               transaction.memo != request.memo ||
+               transaction.tx_type != request.tx_type {
+                panic!("Idempotency key reused with different parameters");
+            }
+            return tx_id;
+        }
         
-        // Store transaction
-        env.storage().instance().set(&DataKey::Transaction(tx_id), &transaction);
-        env.storage().instance().set(&DataKey::TransactionCounter, &tx_id);
+        if matches!(request.tx_type, TxType::Batch) {
+            panic!("Use create_batch_transaction for batches");
+        }
         
-        // If doesn't need approval, execute immediately
-        if !requires_approval {
-            Self::execute_transaction_internal(&env, tx_id);
+        Self::create_transaction_internal(&env, vault_id, NewTransaction {
+            from_wallet: request.from_wallet,
+            to_address: request.to_address,
+            amount: request.amount,
+            memo: request.memo,
+            tx_type: request.tx_type,
+            legs: None,
+            idempotency_key: Some(idempotency_key),
+        })
+    }
+    
+    /// Create a batch of payments approved and executed together
+    pub fn create_batch_transaction(
+        env: Env,
+        vault_id: u32,
+        from_wallet: Address,
+        legs: Vec<(Address, i128, Memo)>,
+    ) -> u64 {
+        from_wallet.require_auth();
+        
+        if legs.is_empty() {
+            panic!("Batch must have at least one leg");
+        }
+        if legs.len() > MAX_BATCH_LEGS {
+            panic!("Too many batch legs");
         }
         
-        log!(&env, "Transaction {} created, requires_approval: {}", tx_id, requires_approval);
-        tx_id
+        // Validate every leg and compute the batch total
+        let mut total: i128 = 0;
+        let mut batch_legs: Vec<BatchLeg> = Vec::new(&env);
+        for (to_address, amount, memo) in legs.iter() {
+            if amount <= 0 {
+                panic!("Amount must be positive");
+            }
+            Self::check_not_denylisted(&env, vault_id, &to_address);
+            Self::check_memo(&memo);
+            
+            total += amount;
+            batch_legs.push_back(BatchLeg {
+                to_address,
+                amount,
+                memo,
+                status: TxStatus::Pending,
+            });
+        }
+        
+        // The batch record points at the contract itself, destinations live in the legs
+        Self::create_transaction_internal(&env, vault_id, NewTransaction {
+            from_wallet,
+            to_address: env.current_contract_address(),
+            amount: total,
+            memo: Memo::None, // memos travel with the legs
+            tx_type: TxType::Batch,
+            legs: Some(batch_legs),
+            idempotency_key: None,
+        })
     }
     
     /// Guardian approves a transaction
     pub fn approve_transaction(
         env: Env,
+        vault_id: u32,
         guardian: Address,
         tx_id: u64,
+        response: BytesN<64>,
     ) -> bool {
         guardian.require_auth();
         
-        Self::check_initialized(&env);
-        Self::check_emergency_mode(&env);
+        Self::check_initialized(&env, vault_id);
+        Self::check_emergency_mode(&env, vault_id);
         
         // Check if guardian exists and is active
-        let guardians: Map<Address, Guardian> = env.storage().instance()
-            .get(&DataKey::Guardians)
-            .unwrap_or_else(|| panic!("Contract not initialized"));
-            
-        let mut guardian_info = guardians.get(guardian.clone()).unwrap_or_else(|| panic!("Not a guardian"));
-        
-        if !guardian_info.is_active {
-            panic!("Guardian not active");
-        }
+        let mut guardian_info = Self::require_acting_guardian(&env, vault_id, &guardian);
         
-        // Get transaction
-        let tx_key = DataKey::Transaction(tx_id);
-        let mut transaction: Transaction = env.storage().instance()
-            .get(&tx_key)
-            .unwrap_or_else(|| panic!("Transaction not found"));
-            
-        // Check if already approved by this guardian
-        if transaction.approvals.contains(&guardian) {
-            panic!("Already approved");
+        let public_key = Self::get_guardian_key(env.clone(), vault_id, guardian.clone());
+        if !Self::check_challenge_response(&env, vault_id, tx_id, public_key, &response) {
+            panic!("Invalid challenge response");
         }
         
-        // Check if transaction is in correct status
-        if !matches!(transaction.status, TxStatus::AwaitingApproval) {
-            panic!("Transaction not awaiting approval");
+        let outcome = Self::apply_approval(&env, vault_id, Approval { guardian: guardian.clone(), delegate: None }, tx_id);
+        match outcome {
+            ApprovalOutcome::NotFound => panic!("Transaction not found"),
+            ApprovalOutcome::AlreadyApproved => panic!("Already approved"),
+            ApprovalOutcome::NotAwaitingApproval => panic!("Transaction not awaiting approval"),
+            ApprovalOutcome::InvalidResponse => panic!("Invalid challenge response"),
+            ApprovalOutcome::ExecutionFailed(failure) => Self::execution_failed(failure),
+            ApprovalOutcome::Recorded | ApprovalOutcome::Executed => {}
         }
         
-        // Add approval
-        transaction.approvals.push_back(guardian.clone());
-        
         // Update guardian stats
         guardian_info.approval_count += 1;
         guardian_info.last_approval = env.ledger().timestamp();
+        Self::save_guardian(&env, vault_id, guardian_info);
         
-        // Check if we have enough approvals
-        let system_limits: SystemLimits = env.storage().instance().get(&DataKey::SystemLimits).unwrap();
-        let has_enough_approvals = transaction.approvals.len() >= system_limits.required_approvals;
+        matches!(outcome, ApprovalOutcome::Executed)
+    }
+    
+    /// Guardian approves several transactions with a single authorization,
+    /// `responses` holds the challenge response for each id in the same order
+    /// (a signature that does not verify reverts the whole call)
+    pub fn approve_transactions(
+        env: Env,
+        vault_id: u32,
+        guardian: Address,
+        tx_ids: Vec<u64>,
+        responses: Vec<BytesN<64>>,
+    ) -> Vec<ApprovalResult> {
+        guardian.require_auth();
         
-        if has_enough_approvals {
-            transaction.status = TxStatus::Approved;
-            Self::execute_transaction_internal(&env, tx_id);
+        Self::check_initialized(&env, vault_id);
+        Self::check_emergency_mode(&env, vault_id);
+        
+        if responses.len() != tx_ids.len() {
+            panic!("One challenge response required per transaction");
         }
         
-        // Update storage
-        env.storage().instance().set(&tx_key, &transaction);
+        let mut guardian_info = Self::require_acting_guardian(&env, vault_id, &guardian);
+        let public_key = Self::get_guardian_key(env.clone(), vault_id, guardian.clone());
         
-        let mut updated_guardians = guardians;
-        updated_guardians.set(guardian.clone(), guardian_info);
-        env.storage().instance().set(&DataKey::Guardians, &updated_guardians);
+        // Ineligible ids are reported back instead of aborting the whole call
+        let mut results: Vec<ApprovalResult> = Vec::new(&env);
+        let mut approved: u32 = 0;
+        for (tx_id, response) in tx_ids.iter().zip(responses.iter()) {
+            let outcome = if Self::check_challenge_response(&env, vault_id, tx_id, public_key.clone(), &response) {
+                Self::apply_approval(&env, vault_id, Approval { guardian: guardian.clone(), delegate: None }, tx_id)
+            } else {
+                ApprovalOutcome::InvalidResponse
+            };
+            if matches!(outcome, ApprovalOutcome::Recorded | ApprovalOutcome::Executed) {
+                approved += 1;
+            }
+            results.push_back(ApprovalResult { tx_id, outcome });
+        }
         
-        log!(&env, "Transaction {} approved by guardian, total approvals: {}", 
-             tx_id, transaction.approvals.len());
-             
-        has_enough_approvals
-    }
-    
-    /// Get transaction details
-    pub fn get_transaction(env: Env, tx_id: u64) -> Option<Transaction> {
-        env.storage().instance().get(&DataKey::Transaction(tx_id))
-    }
-    
-    /// Get guardian information
-    pub fn get_guardian(env: Env, guardian_address: Address) -> Option<Guardian> {
-        let guardians: Option<Map<Address, Guardian>> = env.storage().instance().get(&DataKey::Guardians);
-        guardians?.get(guardian_address.clone())
-    }
-    
-    /// Get wallet balance
-    pub fn get_wallet_balance(env: Env, wallet_address: Address) -> Option<i128> {
-        let wallet_info: Option<WalletInfo> = env.storage().instance()
-            .get(&DataKey::WalletInfo(wallet_address));
-        wallet_info.map(|w| w.balance)
+        if approved > 0 {
+            guardian_info.approval_count += approved;
+            guardian_info.last_approval = env.ledger().timestamp();
+            Self::save_guardian(&env, vault_id, guardian_info);
+        }
+        
+        log!(&env, "Guardian approved {} of {} transactions", approved, tx_ids.len());
+        results
+    }
+    
+    /// Let a backup signer approve on the guardian's behalf, up to `max_amount`
+    /// and until `expires_at` (replaces any previous delegation)
+    pub fn delegate(
+        env: Env,
+        vault_id: u32,
+        guardian: Address,
+        delegate: Address,
+        public_key: BytesN<32>,
+        expires_at: u64,
+        max_amount: i128,
+    ) {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        if expires_at <= env.ledger().timestamp() {
+            panic!("Delegation already expired");
+        }
+        if max_amount <= 0 {
+            panic!("Max amount must be positive");
+        }
+        if Self::get_guardian(env.clone(), vault_id, delegate.clone()).is_some() {
+            panic!("Cannot delegate to a guardian");
+        }
+        
+        let delegation = Delegation {
+            delegate,
+            public_key,
+            expires_at,
+            max_amount,
+        };
+        env.save(&(vault_id, DataKey::Delegation(guardian)), &delegation);
+        
+        log!(&env, "Guardian delegated approvals until {}", expires_at);
+    }
+    
+    /// Revoke the guardian's delegation
+    pub fn revoke_delegation(env: Env, vault_id: u32, guardian: Address) {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        env.storage().persistent().remove(&(vault_id, DataKey::Delegation(guardian)));
+        
+        log!(&env, "Guardian delegation revoked");
+    }
+    
+    /// Get a guardian's current delegation
+    pub fn get_delegation(env: Env, vault_id: u32, guardian: Address) -> Option<Delegation> {
+        env.load(&(vault_id, DataKey::Delegation(guardian)))
+    }
+    
+    /// Delegate approves on a guardian's behalf
+    pub fn approve_as_delegate(
+        env: Env,
+        vault_id: u32,
+        delegate: Address,
+        guardian: Address,
+        tx_id: u64,
+        response: BytesN<64>,
+    ) -> bool {
+        delegate.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::check_emergency_mode(&env, vault_id);
+        
+        let mut guardian_info = Self::require_active_guardian(&env, vault_id, &guardian);
+        
+        // Check the delegation scope
+        let delegation = Self::get_delegation(env.clone(), vault_id, guardian.clone())
+            .unwrap_or_else(|| panic!("No delegation"));
+        if delegation.delegate != delegate {
+            panic!("Not the guardian's delegate");
+        }
+        if env.ledger().timestamp() > delegation.expires_at {
+            panic!("Delegation expired");
+        }
+        let transaction: Transaction = env.load(&(vault_id, DataKey::Transaction(tx_id)))
+            .unwrap_or_else(|| panic!("Transaction not found"));
+        if transaction.amount > delegation.max_amount {
+            panic!("Amount exceeds delegation scope");
+        }
+        
+        if !Self::check_challenge_response(&env, vault_id, tx_id, Some(delegation.public_key), &response) {
+            panic!("Invalid challenge response");
+        }
+        
+        let approval = Approval {
+            guardian,
+            delegate: Some(delegate),
+        };
+        let outcome = Self::apply_approval(&env, vault_id, approval, tx_id);
+        match outcome {
+            ApprovalOutcome::NotFound => panic!("Transaction not found"),
+            ApprovalOutcome::AlreadyApproved => panic!("Already approved"),
+            ApprovalOutcome::NotAwaitingApproval => panic!("Transaction not awaiting approval"),
+            ApprovalOutcome::InvalidResponse => panic!("Invalid challenge response"),
+            ApprovalOutcome::ExecutionFailed(failure) => Self::execution_failed(failure),
+            ApprovalOutcome::Recorded | ApprovalOutcome::Executed => {}
+        }
+        
+        // Delegated votes are not guardian proof of life, last_approval is left untouched
+        guardian_info.approval_count += 1;
+        Self::save_guardian(&env, vault_id, guardian_info);
+        
+        matches!(outcome, ApprovalOutcome::Executed)
+    }
+    
+    /// Register a guardian's ed25519 public key
+    pub fn register_guardian_key(env: Env, vault_id: u32, guardian: Address, public_key: BytesN<32>) {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        env.save(&(vault_id, DataKey::GuardianKey(guardian)), &public_key);
+        
+        log!(&env, "Guardian signing key registered");
+    }
+    
+    /// Get a guardian's registered ed25519 public key
+    pub fn get_guardian_key(env: Env, vault_id: u32, guardian: Address) -> Option<BytesN<32>> {
+        env.load(&(vault_id, DataKey::GuardianKey(guardian)))
+    }
+    
+    /// Get the payload guardians sign off-chain to approve a transaction
+    pub fn get_approval_payload(env: Env, vault_id: u32, tx_id: u64) -> Bytes {
+        let transaction: Transaction = env.load(&(vault_id, DataKey::Transaction(tx_id)))
+            .unwrap_or_else(|| panic!("Transaction not found"));
+        
+        Self::approval_payload(&env, vault_id, &transaction)
+    }
+    
+    /// Relay guardian approvals signed off-chain with their ed25519 keys
+    pub fn approve_with_signatures(
+        env: Env,
+        vault_id: u32,
+        tx_id: u64,
+        signatures: Vec<GuardianSignature>,
+    ) -> bool {
+        Self::check_initialized(&env, vault_id);
+        Self::check_emergency_mode(&env, vault_id);
+        
+        if signatures.is_empty() {
+            panic!("No signatures provided");
+        }
+        
+        let transaction: Transaction = env.load(&(vault_id, DataKey::Transaction(tx_id)))
+            .unwrap_or_else(|| panic!("Transaction not found"));
+        let payload = Self::approval_payload(&env, vault_id, &transaction);
+        
+        let mut executed = false;
+        for entry in signatures.iter() {
+            let mut guardian_info = Self::require_acting_guardian(&env, vault_id, &entry.guardian);
+            let public_key: BytesN<32> = env.load(&(vault_id, DataKey::GuardianKey(entry.guardian.clone())))
+                .unwrap_or_else(|| panic!("Guardian key not registered"));
+            
+            // Traps on an invalid signature, reverting the whole submission
+            env.crypto().ed25519_verify(&public_key, &payload, &entry.signature);
+            
+            match Self::apply_approval(&env, vault_id, Approval { guardian: entry.guardian.clone(), delegate: None }, tx_id) {
+                ApprovalOutcome::NotFound => panic!("Transaction not found"),
+                ApprovalOutcome::AlreadyApproved => panic!("Already approved"),
+                ApprovalOutcome::NotAwaitingApproval => panic!("Transaction not awaiting approval"),
+                ApprovalOutcome::InvalidResponse => panic!("Invalid challenge response"),
+                ApprovalOutcome::ExecutionFailed(failure) => Self::execution_failed(failure),
+                ApprovalOutcome::Recorded => {}
+                ApprovalOutcome::Executed => executed = true,
+            }
+            
+            guardian_info.approval_count += 1;
+            guardian_info.last_approval = env.ledger().timestamp();
+            Self::save_guardian(&env, vault_id, guardian_info);
+            
+            if executed {
+                break;
+            }
+        }
+        
+        // Consume the nonce so the same signatures cannot be replayed
+        let nonce_key = (vault_id, DataKey::SignatureNonce(tx_id));
+        let nonce: u64 = env.load(&nonce_key).unwrap_or(0);
+        env.save(&nonce_key, &(nonce + 1));
+        
+        executed
+    }
+    
+    /// Get the challenge guardians answer when approving a transaction
+    pub fn get_challenge(env: Env, vault_id: u32, tx_id: u64) -> Option<BytesN<32>> {
+        env.load(&(vault_id, DataKey::Challenge(tx_id)))
+    }
+    
+    /// Get transaction details
+    pub fn get_transaction(env: Env, vault_id: u32, tx_id: u64) -> Option<Transaction> {
+        env.load(&(vault_id, DataKey::Transaction(tx_id)))
+    }
+    
+    /// Get the individual legs of a batch transaction
+    pub fn get_batch_legs(env: Env, vault_id: u32, tx_id: u64) -> Option<Vec<BatchLeg>> {
+        env.load(&(vault_id, DataKey::BatchLegs(tx_id)))
+    }
+    
+    /// List transaction summaries matching a filter. `cursor` is the position to
+    /// start from (0 for the first page) and `next_cursor` continues the listing.
+    pub fn list_transactions(env: Env, vault_id: u32, filter: TxFilter, cursor: u32, limit: u32) -> TxPage {
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        
+        let index = match filter {
+            TxFilter::All => {
+                // Ids are sequential, no index needed
+                let counter: u64 = env.load(&(vault_id, DataKey::TransactionCounter)).unwrap_or(0);
+                let last = counter.min(cursor as u64 + limit as u64);
+                let mut ids = Vec::new(&env);
+                for tx_id in (cursor as u64 + 1)..=last {
+                    ids.push_back(tx_id);
+                }
+                return Self::summarize_page(&env, vault_id, ids, limit, cursor, counter > last);
+            }
+            index => index,
+        };
+        
+        let ids = Self::index_read(&env, vault_id, &index, cursor, limit);
+        let has_more = Self::index_len(&env, vault_id, &index) > cursor.saturating_add(limit);
+        Self::summarize_page(&env, vault_id, ids, limit, cursor, has_more)
+    }
+    
+    /// Get guardian information
+    pub fn get_guardian(env: Env, vault_id: u32, guardian_address: Address) -> Option<Guardian> {
+        let guardians: Option<Map<Address, Guardian>> = env.load(&(vault_id, DataKey::Guardians));
+        guardians?.get(guardian_address.clone())
+    }
+    
+    /// List all guardians with their approval stats
+    pub fn list_guardians(env: Env, vault_id: u32) -> Vec<Guardian> {
+        let guardians: Map<Address, Guardian> = env.load(&(vault_id, DataKey::Guardians))
+            .unwrap_or_else(|| Map::new(&env));
+        guardians.values()
+    }
+    
+    /// Get full wallet information
+    pub fn get_wallet_info(env: Env, vault_id: u32, wallet_address: Address) -> Option<WalletView> {
+        let wallet_info: Option<WalletInfo> = env.load(&(vault_id, DataKey::WalletInfo(wallet_address)));
+        wallet_info.map(|info| WalletView {
+            available_balance: info.balance - info.reserved_balance,
+            info,
+        })
+    }
+    
+    /// Get the deployment admin allowed to create vaults
+    pub fn get_admin(env: Env) -> Address {
+        env.storage().instance().get(&GlobalKey::Admin).unwrap()
+    }
+    
+    /// Get the number of vaults hosted by this deployment
+    pub fn get_vault_count(env: Env) -> u32 {
+        env.storage().instance().get(&GlobalKey::VaultCounter).unwrap_or(0)
+    }
+    
+    /// Get a snapshot of the vault configuration
+    pub fn get_config(env: Env, vault_id: u32) -> ContractConfig {
+        Self::check_initialized(&env, vault_id);
+        
+        let hot_wallet: Address = env.load(&(vault_id, DataKey::HotWallet)).unwrap();
+        let cold_wallet: Address = env.load(&(vault_id, DataKey::ColdWallet)).unwrap();
+        
+        ContractConfig {
+            system_limits: env.load(&(vault_id, DataKey::SystemLimits)).unwrap(),
+            hot_wallet: Self::get_wallet_info(env.clone(), vault_id, hot_wallet).unwrap(),
+            cold_wallet: Self::get_wallet_info(env.clone(), vault_id, cold_wallet).unwrap(),
+            guardian_count: env.load(&(vault_id, DataKey::GuardianCount)).unwrap_or(0),
+            active_guardian_count: Self::active_guardian_count(&env, vault_id),
+            emergency_mode: Self::is_emergency_mode(env.clone(), vault_id),
+            emergency_initiator: env.load(&(vault_id, DataKey::EmergencyInitiator)),
+            compliance_officer: env.load(&(vault_id, DataKey::ComplianceOfficer)),
+            transaction_counter: Self::get_transaction_counter(env.clone(), vault_id),
+        }
+    }
+    
+    /// Get spent and remaining amounts for the current day and month
+    pub fn get_spending_status(env: Env, vault_id: u32) -> SpendingStatus {
+        Self::check_initialized(&env, vault_id);
+        
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        let today = Self::current_day(&env);
+        let current_month = today / 30;
+        
+        let daily_spent = Self::get_period_spending(env.clone(), vault_id, PeriodKey::Day(today), None);
+        let monthly_spent = Self::get_period_spending(env.clone(), vault_id, PeriodKey::Month(current_month), None);
+        
+        let mut addresses: Vec<Address> = Vec::new(&env);
+        addresses.push_back(env.load(&(vault_id, DataKey::HotWallet)).unwrap());
+        addresses.push_back(env.load(&(vault_id, DataKey::ColdWallet)).unwrap());
+        for named in Self::list_named_wallets(env.clone(), vault_id).iter() {
+            addresses.push_back(named.address);
+        }
+        
+        let mut wallets: Vec<WalletSpending> = Vec::new(&env);
+        for wallet in addresses.iter() {
+            wallets.push_back(WalletSpending {
+                daily_spent: Self::get_period_spending(env.clone(), vault_id, PeriodKey::Day(today), Some(wallet.clone())),
+                monthly_spent: Self::get_period_spending(env.clone(), vault_id, PeriodKey::Month(current_month), Some(wallet.clone())),
+                wallet,
+            });
+        }
+        
+        SpendingStatus {
+            daily: PeriodSpending {
+                period_key: today,
+                spent: daily_spent,
+                limit: system_limits.daily_limit,
+                remaining: (system_limits.daily_limit - daily_spent).max(0),
+            },
+            monthly: PeriodSpending {
+                period_key: current_month,
+                spent: monthly_spent,
+                limit: system_limits.monthly_limit,
+                remaining: (system_limits.monthly_limit - monthly_spent).max(0),
+            },
+            wallets,
+        }
+    }
+    
+    /// Get the amount spent in a day or month, overall or by a wallet
+    pub fn get_period_spending(env: Env, vault_id: u32, period: PeriodKey, wallet: Option<Address>) -> i128 {
+        let key = match (period, wallet) {
+            (PeriodKey::Day(day), None) => (vault_id, DataKey::DailySpent(day)),
+            (PeriodKey::Month(month), None) => (vault_id, DataKey::MonthlySpent(month)),
+            (PeriodKey::Day(day), Some(wallet)) => (vault_id, DataKey::WalletDailySpent(wallet, day)),
+            (PeriodKey::Month(month), Some(wallet)) => (vault_id, DataKey::WalletMonthlySpent(wallet, month)),
+        };
+        env.load(&key).unwrap_or(0)
+    }
+    
+    /// Get wallet balance
+    pub fn get_wallet_balance(env: Env, vault_id: u32, wallet_address: Address) -> Option<i128> {
+        let wallet_info: Option<WalletInfo> = env.load(&(vault_id, DataKey::WalletInfo(wallet_address)));
+        wallet_info.map(|w| w.balance)
     }
     
     /// Get hot wallet balance
-    pub fn get_hot_balance(env: Env) -> i128 {
-        let hot_wallet: Address = env.storage().instance().get(&DataKey::HotWallet).unwrap();
-        Self::get_wallet_balance(env, hot_wallet).unwrap_or(0)
+    pub fn get_hot_balance(env: Env, vault_id: u32) -> i128 {
+        let hot_wallet: Address = env.load(&(vault_id, DataKey::HotWallet)).unwrap();
+        Self::get_wallet_balance(env, vault_id, hot_wallet).unwrap_or(0)
+    }
+    
+    /// Get cold wallet balance
+    pub fn get_cold_balance(env: Env, vault_id: u32) -> i128 {
+        let cold_wallet: Address = env.load(&(vault_id, DataKey::ColdWallet)).unwrap();
+        Self::get_wallet_balance(env, vault_id, cold_wallet).unwrap_or(0)
+    }
+    
+    /// Emergency shutdown
+    pub fn emergency_shutdown(env: Env, vault_id: u32, guardian: Address) {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        
+        // Verify guardian
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        // Activate emergency mode
+        env.save(&(vault_id, DataKey::EmergencyMode), &true);
+        env.save(&(vault_id, DataKey::EmergencyInitiator), &guardian);
+        
+        log!(&env, "Emergency shutdown activated by guardian");
+    }
+    
+    /// Pause a single operation type (any active guardian)
+    pub fn pause_operation(env: Env, vault_id: u32, guardian: Address, operation: Operation) {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        env.save(&(vault_id, DataKey::Paused(operation)), &true);
+        
+        log!(&env, "Operation paused by guardian");
+    }
+    
+    /// Propose lifting a pause (guardian quorum)
+    pub fn propose_unpause(env: Env, vault_id: u32, guardian: Address, operation: Operation) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        if !Self::is_operation_paused(env.clone(), vault_id, operation.clone()) {
+            panic!("Operation not paused");
+        }
+        
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::Unpause(operation), system_limits.required_approvals)
+    }
+    
+    /// Check if an operation type is paused
+    pub fn is_operation_paused(env: Env, vault_id: u32, operation: Operation) -> bool {
+        env.load(&(vault_id, DataKey::Paused(operation))).unwrap_or(false)
+    }
+    
+    /// Get system info
+    pub fn get_system_limits(env: Env, vault_id: u32) -> SystemLimits {
+        env.load(&(vault_id, DataKey::SystemLimits)).unwrap()
+    }
+    
+    /// Check if emergency mode is active
+    pub fn is_emergency_mode(env: Env, vault_id: u32) -> bool {
+        env.load(&(vault_id, DataKey::EmergencyMode)).unwrap_or(false)
+    }
+    
+    /// Get transaction counter
+    pub fn get_transaction_counter(env: Env, vault_id: u32) -> u64 {
+        env.load(&(vault_id, DataKey::TransactionCounter)).unwrap_or(0)
+    }
+    
+    // ==================== GOVERNANCE ====================
+    
+    /// Propose new system limits. Tightening needs the usual quorum, loosening
+    /// any limit needs every active guardian. The proposer's approval is counted.
+    pub fn propose_limits_update(env: Env, vault_id: u32, guardian: Address, new_limits: SystemLimits) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        Self::validate_limits(&new_limits, Self::active_guardian_count(&env, vault_id));
+        let required_approvals = Self::limits_update_approvals(&env, vault_id, &new_limits);
+        
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::UpdateLimits(new_limits), required_approvals)
+    }
+    
+    /// Propose a weighted voting policy (every active guardian)
+    pub fn propose_voting_policy(env: Env, vault_id: u32, guardian: Address, policy: VotingPolicy) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        Self::validate_voting_policy(&env, vault_id, &policy);
+        
+        // Redistributing voting power can hand control to a subset of guardians
+        let active_guardians = Self::active_guardian_count(&env, vault_id);
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::SetVotingPolicy(policy), active_guardians)
+    }
+    
+    /// Propose a velocity cap for one destination, or the default for all
+    /// destinations when `destination` is None; a None limit removes the cap
+    pub fn propose_destination_limit(
+        env: Env,
+        vault_id: u32,
+        guardian: Address,
+        destination: Option<Address>,
+        limit: Option<DestinationLimit>,
+    ) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        let action = match limit {
+            Some(limit) => {
+                if limit.daily_amount <= 0 || limit.daily_amount > limit.monthly_amount {
+                    panic!("Daily amount must be positive and not exceed monthly amount");
+                }
+                if limit.daily_count == 0 || limit.daily_count > limit.monthly_count {
+                    panic!("Daily count must be positive and not exceed monthly count");
+                }
+                ProposalAction::SetDestinationLimit(DestinationLimitUpdate { destination, limit })
+            }
+            None => ProposalAction::RemoveDestinationLimit(destination),
+        };
+        
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        Self::create_proposal(&env, vault_id, guardian, action, system_limits.required_approvals)
+    }
+    
+    /// Get the velocity cap that applies to a destination
+    pub fn get_destination_limit(env: Env, vault_id: u32, destination: Address) -> Option<DestinationLimit> {
+        env.load(&(vault_id, DataKey::DestinationLimit(destination)))
+            .or_else(|| env.load(&(vault_id, DataKey::DefaultDestinationLimit)))
+    }
+    
+    /// Get what a destination has been sent in a day or month
+    pub fn get_destination_usage(env: Env, vault_id: u32, destination: Address, period: PeriodKey) -> DestinationUsage {
+        let key = match period {
+            PeriodKey::Day(day) => DataKey::DestinationDaily(destination, day),
+            PeriodKey::Month(month) => DataKey::DestinationMonthly(destination, month),
+        };
+        env.load(&(vault_id, key))
+            .unwrap_or(DestinationUsage { amount: 0, count: 0 })
+    }
+    
+    /// Propose limits in a reference currency priced by a SEP-40 oracle (every active guardian)
+    pub fn propose_reference_pricing(env: Env, vault_id: u32, guardian: Address, pricing: ReferencePricing) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        let active_guardians = Self::active_guardian_count(&env, vault_id);
+        Self::validate_limits(&pricing.limits, active_guardians);
+        if pricing.oracle.max_age == 0 {
+            panic!("Max price age must be positive");
+        }
+        
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::SetReferencePricing(pricing), active_guardians)
+    }
+    
+    /// Propose the custody fee schedule (guardian quorum)
+    pub fn propose_fee_schedule(env: Env, vault_id: u32, guardian: Address, fee_schedule: FeeSchedule) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        if fee_schedule.flat_fee < 0 {
+            panic!("Flat fee must not be negative");
+        }
+        if fee_schedule.fee_bps as i128 > BPS_DENOMINATOR || fee_schedule.aum_fee_bps as i128 > BPS_DENOMINATOR {
+            panic!("Fee rate must not exceed 10000 bps");
+        }
+        Self::check_fee_wallet(&env, vault_id, &fee_schedule.fee_wallet);
+        
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::SetFeeSchedule(fee_schedule), system_limits.required_approvals)
+    }
+    
+    /// Collect the accrued AUM fee into the fee wallet (permissionless)
+    pub fn collect_aum_fee(env: Env, vault_id: u32) -> i128 {
+        Self::check_initialized(&env, vault_id);
+        
+        let mut fee_state: FeeState = env.load(&(vault_id, FeeKey::State))
+            .unwrap_or_else(|| panic!("Fees not configured"));
+        
+        let now = env.ledger().timestamp();
+        let elapsed = (now - fee_state.last_aum_collection) as i128;
+        
+        let mut wallets: Vec<Address> = Vec::new(&env);
+        wallets.push_back(env.load(&(vault_id, DataKey::HotWallet)).unwrap());
+        wallets.push_back(env.load(&(vault_id, DataKey::ColdWallet)).unwrap());
+        for wallet in Self::list_named_wallets(env.clone(), vault_id).iter() {
+            wallets.push_back(wallet.address);
+        }
+        
+        // Pro-rated on the balance not committed to pending transactions
+        let mut total: i128 = 0;
+        for wallet in wallets.iter() {
+            let wallet_key = (vault_id, DataKey::WalletInfo(wallet));
+            let mut wallet_info: WalletInfo = env.load(&wallet_key).unwrap();
+            let available = wallet_info.balance - wallet_info.reserved_balance;
+            let fee = available * fee_state.schedule.aum_fee_bps as i128 * elapsed / (BPS_DENOMINATOR * SECONDS_PER_YEAR);
+            if fee > 0 {
+                wallet_info.balance -= fee;
+                env.save(&wallet_key, &wallet_info);
+                total += fee;
+            }
+        }
+        
+        Self::record_fees(&env, vault_id, &fee_state.schedule.fee_wallet, 0, total);
+        fee_state.last_aum_collection = now;
+        env.save(&(vault_id, FeeKey::State), &fee_state);
+        
+        log!(&env, "AUM fee of {} collected", total);
+        total
+    }
+    
+    /// Get the custody fee schedule
+    pub fn get_fee_schedule(env: Env, vault_id: u32) -> Option<FeeSchedule> {
+        let fee_state: Option<FeeState> = env.load(&(vault_id, FeeKey::State));
+        fee_state.map(|fee_state| fee_state.schedule)
+    }
+    
+    /// Get the fees collected in a day or month
+    pub fn get_fees(env: Env, vault_id: u32, period: PeriodKey) -> FeeLedgerEntry {
+        env.load(&(vault_id, FeeKey::Ledger(period)))
+            .unwrap_or(FeeLedgerEntry { transaction_fees: 0, aum_fees: 0 })
+    }
+    
+    /// Get the price oracle configuration, if any
+    pub fn get_oracle_config(env: Env, vault_id: u32) -> Option<OracleConfig> {
+        env.load(&(vault_id, DataKey::OracleConfig))
+    }
+    
+    /// Get the weighted voting policy, if one is set
+    pub fn get_voting_policy(env: Env, vault_id: u32) -> Option<VotingPolicy> {
+        env.load(&(vault_id, DataKey::VotingPolicy))
+    }
+    
+    /// Guardian approves a governance proposal
+    pub fn approve_proposal(env: Env, vault_id: u32, guardian: Address, proposal_id: u64) -> bool {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        let mut guardian_info = Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        let proposal_key = (vault_id, DataKey::Proposal(proposal_id));
+        let mut proposal: Proposal = env.load(&proposal_key)
+            .unwrap_or_else(|| panic!("Proposal not found"));
+        
+        if !matches!(proposal.status, ProposalStatus::Pending) {
+            panic!("Proposal not pending");
+        }
+        if env.ledger().timestamp() > proposal.expires_at {
+            panic!("Proposal expired");
+        }
+        proposal.approvals = Self::active_approvers(&env, vault_id, &proposal.approvals);
+        if proposal.approvals.contains(&guardian) {
+            panic!("Already approved");
+        }
+        
+        proposal.approvals.push_back(guardian.clone());
+        
+        guardian_info.approval_count += 1;
+        guardian_info.last_approval = env.ledger().timestamp();
+        Self::save_guardian(&env, vault_id, guardian_info);
+        
+        let executed = Self::try_execute_proposal(&env, vault_id, &mut proposal);
+        env.save(&proposal_key, &proposal);
+        
+        log!(&env, "Proposal {} approved by guardian, total approvals: {}", 
+             proposal_id, proposal.approvals.len());
+        executed
+    }
+    
+    /// Propose a named sub-account with its own limits and approval policy
+    pub fn propose_wallet_creation(env: Env, vault_id: u32, guardian: Address, wallet: NamedWallet) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        if env.exists(&(vault_id, DataKey::NamedWallet(wallet.name.clone()))) {
+            panic!("Wallet name already used");
+        }
+        if env.exists(&(vault_id, DataKey::WalletInfo(wallet.address.clone()))) {
+            panic!("Wallet already registered");
+        }
+        if matches!(wallet.wallet_type, WalletType::Fee) {
+            panic!("Fee wallet is set by the fee schedule");
+        }
+        Self::validate_policy(&wallet.policy, Self::active_guardian_count(&env, vault_id));
+        
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::CreateWallet(wallet), system_limits.required_approvals)
+    }
+    
+    /// Propose retiring an empty named sub-account
+    pub fn propose_wallet_retirement(env: Env, vault_id: u32, guardian: Address, name: Symbol) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        if !env.exists(&(vault_id, DataKey::NamedWallet(name.clone()))) {
+            panic!("Wallet not found");
+        }
+        
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::RetireWallet(name), system_limits.required_approvals)
+    }
+    
+    /// Get a named sub-account
+    pub fn get_named_wallet(env: Env, vault_id: u32, name: Symbol) -> Option<NamedWallet> {
+        env.load(&(vault_id, DataKey::NamedWallet(name)))
+    }
+    
+    /// List all named sub-accounts, including retired ones
+    pub fn list_named_wallets(env: Env, vault_id: u32) -> Vec<NamedWallet> {
+        let names: Vec<Symbol> = env.load(&(vault_id, DataKey::WalletNames)).unwrap_or_else(|| Vec::new(&env));
+        let mut wallets = Vec::new(&env);
+        for name in names.iter() {
+            wallets.push_back(env.load(&(vault_id, DataKey::NamedWallet(name))).unwrap());
+        }
+        wallets
+    }
+    
+    /// Get governance proposal details
+    pub fn get_proposal(env: Env, vault_id: u32, proposal_id: u64) -> Option<Proposal> {
+        env.load(&(vault_id, DataKey::Proposal(proposal_id)))
+    }
+    
+    /// Get previously active limit sets, oldest first
+    pub fn get_limits_history(env: Env, vault_id: u32) -> Vec<LimitsRecord> {
+        env.load(&(vault_id, DataKey::LimitsHistory)).unwrap_or_else(|| Vec::new(&env))
+    }
+    
+    // ==================== GUARDIAN RECOVERY ====================
+    
+    /// Propose the recovery guardian configuration (guardian quorum)
+    pub fn propose_recovery_config(env: Env, vault_id: u32, guardian: Address, config: RecoveryConfig) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        Self::validate_recovery_config(&env, vault_id, &config);
+        
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::SetRecoveryConfig(config), system_limits.required_approvals)
+    }
+    
+    /// Recovery guardian starts replacing the operational guardian set
+    pub fn initiate_recovery(env: Env, vault_id: u32, recovery_guardian: Address, new_guardians: Vec<Guardian>) -> u64 {
+        recovery_guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        let config = Self::require_recovery_guardian(&env, vault_id, &recovery_guardian);
+        
+        if env.exists(&(vault_id, DataKey::PendingRecovery)) {
+            panic!("Recovery already pending");
+        }
+        Self::validate_guardian_set(&new_guardians);
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        Self::validate_limits(&system_limits, new_guardians.len());
+        
+        let counter: u64 = env.load(&(vault_id, DataKey::RecoveryCounter)).unwrap_or(0);
+        let recovery_id = counter + 1;
+        
+        let mut approvals = Vec::new(&env);
+        approvals.push_back(recovery_guardian.clone());
+        
+        let mut recovery = RecoveryRequest {
+            id: recovery_id,
+            initiator: recovery_guardian.clone(),
+            new_guardians,
+            approvals,
+            status: RecoveryStatus::Pending,
+            initiated_at: env.ledger().timestamp(),
+            executable_at: None,
+            cancelled_by: None,
+        };
+        Self::schedule_recovery_if_ready(&env, vault_id, &config, &mut recovery);
+        
+        env.save(&(vault_id, DataKey::Recovery(recovery_id)), &recovery);
+        env.save(&(vault_id, DataKey::RecoveryCounter), &recovery_id);
+        env.save(&(vault_id, DataKey::PendingRecovery), &recovery_id);
+        
+        RecoveryInitiated {
+            vault_id,
+            recovery_id,
+            initiator: recovery_guardian,
+        }.publish(&env);
+        
+        log!(&env, "Recovery {} initiated", recovery_id);
+        recovery_id
+    }
+    
+    /// Recovery guardian supports a pending recovery
+    pub fn approve_recovery(env: Env, vault_id: u32, recovery_guardian: Address, recovery_id: u64) {
+        recovery_guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        let config = Self::require_recovery_guardian(&env, vault_id, &recovery_guardian);
+        
+        let recovery_key = (vault_id, DataKey::Recovery(recovery_id));
+        let mut recovery = Self::pending_recovery(&env, &recovery_key);
+        
+        if recovery.approvals.contains(&recovery_guardian) {
+            panic!("Already approved");
+        }
+        recovery.approvals.push_back(recovery_guardian);
+        Self::schedule_recovery_if_ready(&env, vault_id, &config, &mut recovery);
+        
+        env.save(&recovery_key, &recovery);
+        
+        log!(&env, "Recovery {} approved, total approvals: {}", recovery_id, recovery.approvals.len());
+    }
+    
+    /// Cancel a pending recovery (any active guardian)
+    pub fn cancel_recovery(env: Env, vault_id: u32, guardian: Address, recovery_id: u64) {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        let recovery_key = (vault_id, DataKey::Recovery(recovery_id));
+        let mut recovery = Self::pending_recovery(&env, &recovery_key);
+        
+        recovery.status = RecoveryStatus::Cancelled;
+        recovery.cancelled_by = Some(guardian.clone());
+        env.save(&recovery_key, &recovery);
+        env.storage().persistent().remove(&(vault_id, DataKey::PendingRecovery));
+        
+        RecoveryCancelled {
+            vault_id,
+            recovery_id,
+            guardian,
+        }.publish(&env);
+        
+        log!(&env, "Recovery {} cancelled by guardian", recovery_id);
+    }
+    
+    /// Replace the guardian set after the timelock (permissionless)
+    pub fn execute_recovery(env: Env, vault_id: u32, recovery_id: u64) {
+        Self::check_initialized(&env, vault_id);
+        
+        let recovery_key = (vault_id, DataKey::Recovery(recovery_id));
+        let mut recovery = Self::pending_recovery(&env, &recovery_key);
+        
+        match recovery.executable_at {
+            Some(executable_at) if env.ledger().timestamp() >= executable_at => {}
+            Some(_) => panic!("Recovery timelock not elapsed"),
+            None => panic!("Recovery threshold not reached"),
+        }
+        
+        let mut guardians_map: Map<Address, Guardian> = Map::new(&env);
+        for guardian in recovery.new_guardians.iter() {
+            guardians_map.set(guardian.address.clone(), guardian);
+        }
+        env.save(&(vault_id, DataKey::Guardians), &guardians_map);
+        env.save(&(vault_id, DataKey::GuardianCount), &recovery.new_guardians.len());
+        // Weights refer to the replaced guardians, fall back to one vote each
+        env.storage().persistent().remove(&(vault_id, DataKey::VotingPolicy));
+        
+        recovery.status = RecoveryStatus::Executed;
+        env.save(&recovery_key, &recovery);
+        env.storage().persistent().remove(&(vault_id, DataKey::PendingRecovery));
+        
+        RecoveryExecuted {
+            vault_id,
+            recovery_id,
+        }.publish(&env);
+        
+        log!(&env, "Recovery {} executed, guardian set replaced", recovery_id);
+    }
+    
+    /// Get the recovery guardian configuration
+    pub fn get_recovery_config(env: Env, vault_id: u32) -> Option<RecoveryConfig> {
+        env.load(&(vault_id, DataKey::RecoveryConfig))
+    }
+    
+    /// Get recovery request details
+    pub fn get_recovery(env: Env, vault_id: u32, recovery_id: u64) -> Option<RecoveryRequest> {
+        env.load(&(vault_id, DataKey::Recovery(recovery_id)))
+    }
+    
+    // ==================== DEAD-MAN SWITCH ====================
+    
+    /// Propose the dead-man switch configuration (guardian quorum)
+    pub fn propose_inheritance_config(env: Env, vault_id: u32, guardian: Address, config: InheritanceConfig) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        if config.inactivity_period < MIN_INACTIVITY_PERIOD {
+            panic!("Inactivity period too short");
+        }
+        if config.timelock < MIN_INHERITANCE_TIMELOCK {
+            panic!("Inheritance timelock too short");
+        }
+        
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::SetInheritance(config), system_limits.required_approvals)
+    }
+    
+    /// Guardian proof of life
+    pub fn heartbeat(env: Env, vault_id: u32, guardian: Address) {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        log!(&env, "Guardian heartbeat recorded");
+    }
+    
+    /// Beneficiary claims the cold funds of an inactive vault
+    pub fn initiate_inheritance(env: Env, vault_id: u32, beneficiary: Address) {
+        beneficiary.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::check_operation_allowed(&env, vault_id, &Operation::ColdWallet);
+        
+        let config: InheritanceConfig = env.load(&(vault_id, DataKey::InheritanceConfig))
+            .unwrap_or_else(|| panic!("Inheritance not configured"));
+        if beneficiary != config.beneficiary {
+            panic!("Not the beneficiary");
+        }
+        
+        let now = env.ledger().timestamp();
+        if now < Self::get_last_guardian_activity(env.clone(), vault_id) + config.inactivity_period {
+            panic!("Guardians still active");
+        }
+        
+        let claim_key = (vault_id, DataKey::InheritanceClaim);
+        let existing: Option<InheritanceClaim> = env.load(&claim_key);
+        if matches!(existing, Some(InheritanceClaim { status: InheritanceStatus::Pending, .. })) {
+            panic!("Inheritance already pending");
+        }
+        
+        // Reserve everything not already committed to other transactions
+        let cold_key = Self::cold_wallet_key(&env, vault_id);
+        let mut cold_wallet: WalletInfo = env.load(&cold_key).unwrap();
+        let amount = cold_wallet.balance - cold_wallet.reserved_balance;
+        if amount <= 0 {
+            panic!("No cold funds to claim");
+        }
+        cold_wallet.reserved_balance += amount;
+        env.save(&cold_key, &cold_wallet);
+        
+        let claim = InheritanceClaim {
+            beneficiary: beneficiary.clone(),
+            amount,
+            status: InheritanceStatus::Pending,
+            initiated_at: now,
+            executable_at: now + config.timelock,
+        };
+        env.save(&claim_key, &claim);
+        
+        InheritanceInitiated {
+            vault_id,
+            beneficiary,
+            amount,
+            executable_at: claim.executable_at,
+        }.publish(&env);
+        
+        log!(&env, "Inheritance claim initiated for {}", amount);
+    }
+    
+    /// Pay out a claim after its timelock (permissionless)
+    pub fn execute_inheritance(env: Env, vault_id: u32) {
+        Self::check_initialized(&env, vault_id);
+        Self::check_operation_allowed(&env, vault_id, &Operation::ColdWallet);
+        
+        let claim_key = (vault_id, DataKey::InheritanceClaim);
+        let mut claim: InheritanceClaim = env.load(&claim_key)
+            .unwrap_or_else(|| panic!("No inheritance claim"));
+        if !matches!(claim.status, InheritanceStatus::Pending) {
+            panic!("Inheritance not pending");
+        }
+        if env.ledger().timestamp() < claim.executable_at {
+            panic!("Inheritance timelock not elapsed");
+        }
+        // Activity that did not cancel the claim itself, such as a custom account signature, still blocks it
+        if Self::get_last_guardian_activity(env.clone(), vault_id) > claim.initiated_at {
+            panic!("Guardians active since the claim");
+        }
+        Self::check_not_denylisted(&env, vault_id, &claim.beneficiary);
+        
+        let cold_key = Self::cold_wallet_key(&env, vault_id);
+        let mut cold_wallet: WalletInfo = env.load(&cold_key).unwrap();
+        cold_wallet.balance -= claim.amount;
+        cold_wallet.reserved_balance -= claim.amount;
+        env.save(&cold_key, &cold_wallet);
+        
+        claim.status = InheritanceStatus::Executed;
+        env.save(&claim_key, &claim);
+        
+        InheritanceExecuted {
+            vault_id,
+            beneficiary: claim.beneficiary,
+            amount: claim.amount,
+        }.publish(&env);
+        
+        log!(&env, "Inheritance claim executed for {}", claim.amount);
+    }
+    
+    /// Get the time of the latest guardian activity
+    pub fn get_last_guardian_activity(env: Env, vault_id: u32) -> u64 {
+        let heartbeat: u64 = env.load(&(vault_id, DataKey::LastHeartbeat)).unwrap_or(0);
+        Self::list_guardians(env, vault_id)
+            .iter()
+            .map(|g| g.last_approval)
+            .fold(heartbeat, u64::max)
+    }
+    
+    /// Get the dead-man switch configuration
+    pub fn get_inheritance_config(env: Env, vault_id: u32) -> Option<InheritanceConfig> {
+        env.load(&(vault_id, DataKey::InheritanceConfig))
+    }
+    
+    /// Get the current or last inheritance claim
+    pub fn get_inheritance_claim(env: Env, vault_id: u32) -> Option<InheritanceClaim> {
+        env.load(&(vault_id, DataKey::InheritanceClaim))
+    }
+    
+    // ==================== SCHEDULED PAYMENTS ====================
+    
+    /// Create a recurring payment schedule, active once the guardian quorum approves
+    pub fn create_schedule(env: Env, vault_id: u32, guardian: Address, payment: ScheduledPayment, recurrence: Recurrence) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::check_operation_allowed(&env, vault_id, &Operation::Payments);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        let ScheduledPayment { from_wallet, to_address, amount, memo } = payment;
+        
+        if amount <= 0 {
+            panic!("Amount must be positive");
+        }
+        if recurrence.interval == 0 {
+            panic!("Interval must be positive");
+        }
+        Self::check_memo(&memo);
+        match recurrence.end {
+            ScheduleEnd::AfterCount(0) => panic!("Schedule must have at least one installment"),
+            ScheduleEnd::At(end_at) if end_at < recurrence.start_at => panic!("Schedule ends before it starts"),
+            _ => {}
+        }
+        if !env.exists(&(vault_id, DataKey::WalletInfo(from_wallet.clone()))) {
+            panic!("Wallet not found");
+        }
+        Self::check_not_denylisted(&env, vault_id, &to_address);
+        
+        let counter: u64 = env.load(&(vault_id, DataKey::ScheduleCounter)).unwrap_or(0);
+        let schedule_id = counter + 1;
+        
+        let mut schedule = PaymentSchedule {
+            id: schedule_id,
+            from_wallet,
+            to_address,
+            amount,
+            memo,
+            next_due: recurrence.start_at,
+            recurrence,
+            executed_count: 0,
+            approvals: Vec::from_array(&env, [guardian]),
+            status: ScheduleStatus::AwaitingApproval,
+            created_at: env.ledger().timestamp(),
+        };
+        if Self::schedule_approved(&env, vault_id, &schedule) {
+            schedule.status = ScheduleStatus::Active;
+        }
+        
+        env.save(&(vault_id, DataKey::Schedule(schedule_id)), &schedule);
+        env.save(&(vault_id, DataKey::ScheduleCounter), &schedule_id);
+        
+        log!(&env, "Schedule {} created", schedule_id);
+        schedule_id
+    }
+    
+    /// Guardian approves a payment schedule
+    pub fn approve_schedule(env: Env, vault_id: u32, guardian: Address, schedule_id: u64) -> bool {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::check_emergency_mode(&env, vault_id);
+        
+        let mut guardian_info = Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        let schedule_key = (vault_id, DataKey::Schedule(schedule_id));
+        let mut schedule: PaymentSchedule = env.load(&schedule_key)
+            .unwrap_or_else(|| panic!("Schedule not found"));
+        
+        schedule.approvals = Self::active_approvers(&env, vault_id, &schedule.approvals);
+        if schedule.approvals.contains(&guardian) {
+            panic!("Already approved");
+        }
+        if !matches!(schedule.status, ScheduleStatus::AwaitingApproval) {
+            panic!("Schedule not awaiting approval");
+        }
+        
+        schedule.approvals.push_back(guardian.clone());
+        
+        let has_enough_approvals = Self::schedule_approved(&env, vault_id, &schedule);
+        if has_enough_approvals {
+            schedule.status = ScheduleStatus::Active;
+        }
+        
+        env.save(&schedule_key, &schedule);
+        
+        guardian_info.approval_count += 1;
+        guardian_info.last_approval = env.ledger().timestamp();
+        Self::save_guardian(&env, vault_id, guardian_info);
+        
+        log!(&env, "Schedule {} approved by guardian, total approvals: {}", 
+             schedule_id, schedule.approvals.len());
+        
+        has_enough_approvals
+    }
+    
+    /// Cancel a payment schedule (any active guardian)
+    pub fn cancel_schedule(env: Env, vault_id: u32, guardian: Address, schedule_id: u64) {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        let schedule_key = (vault_id, DataKey::Schedule(schedule_id));
+        let mut schedule: PaymentSchedule = env.load(&schedule_key)
+            .unwrap_or_else(|| panic!("Schedule not found"));
+        
+        if matches!(schedule.status, ScheduleStatus::Completed | ScheduleStatus::Cancelled) {
+            panic!("Schedule already closed");
+        }
+        
+        schedule.status = ScheduleStatus::Cancelled;
+        env.save(&schedule_key, &schedule);
+        
+        log!(&env, "Schedule {} cancelled by guardian", schedule_id);
+    }
+    
+    /// Execute the next due installment (permissionless)
+    pub fn execute_due(env: Env, vault_id: u32, schedule_id: u64) -> u64 {
+        Self::check_initialized(&env, vault_id);
+        Self::check_operation_allowed(&env, vault_id, &Operation::Payments);
+        
+        let schedule_key = (vault_id, DataKey::Schedule(schedule_id));
+        let mut schedule: PaymentSchedule = env.load(&schedule_key)
+            .unwrap_or_else(|| panic!("Schedule not found"));
+        
+        if !matches!(schedule.status, ScheduleStatus::Active) {
+            panic!("Schedule not active");
+        }
+        if env.ledger().timestamp() < schedule.next_due {
+            panic!("Installment not due");
+        }
+        
+        // Installments always count against the spending limits
+        if let Some(value) = Self::reference_value(&env, vault_id, schedule.amount) {
+            Self::check_spending_limits(&env, vault_id, value);
+        }
+        
+        let tx_id = Self::create_transaction_internal(&env, vault_id, NewTransaction {
+            from_wallet: schedule.from_wallet.clone(),
+            to_address: schedule.to_address.clone(),
+            amount: schedule.amount,
+            memo: schedule.memo.clone(),
+            tx_type: TxType::Payment,
+            legs: None,
+            idempotency_key: None,
+        });
+        
+        // The schedule approvals of guardians still active carry over to the installment.
+        // If they fall short of its approval tier, it waits for further approvals
+        let tx_key = (vault_id, DataKey::Transaction(tx_id));
+        let mut transaction: Transaction = env.load(&tx_key).unwrap();
+        if matches!(transaction.status, TxStatus::AwaitingApproval) {
+            for guardian in Self::active_approvers(&env, vault_id, &schedule.approvals).iter() {
+                Self::index_push(&env, vault_id, TxFilter::Guardian(guardian.clone()), tx_id);
+                transaction.approval_weight += Self::guardian_weight(&env, vault_id, &guardian);
+                transaction.approvals.push_back(Approval { guardian, delegate: None });
+            }
+            let approved = Self::approval_threshold_met(
+                &env, vault_id, &transaction.from_wallet, transaction.value, transaction.approval_weight, transaction.approvals.len(),
+            );
+            if approved {
+                Self::index_status_change(&env, vault_id, tx_id, &transaction.status, &TxStatus::Approved);
+                transaction.status = TxStatus::Approved;
+            }
+            env.save(&tx_key, &transaction);
+            if approved {
+                if let Err(failure) = Self::execute_transaction_internal(&env, vault_id, tx_id) {
+                    Self::execution_failed(failure);
+                }
+            }
+        }
+        
+        schedule.executed_count += 1;
+        schedule.next_due += schedule.recurrence.interval;
+        
+        let finished = match schedule.recurrence.end {
+            ScheduleEnd::Never => false,
+            ScheduleEnd::At(end_at) => schedule.next_due > end_at,
+            ScheduleEnd::AfterCount(count) => schedule.executed_count >= count,
+        };
+        if finished {
+            schedule.status = ScheduleStatus::Completed;
+        }
+        
+        env.save(&schedule_key, &schedule);
+        
+        log!(&env, "Schedule {} installment {} executed as transaction {}", 
+             schedule_id, schedule.executed_count, tx_id);
+        tx_id
+    }
+    
+    /// Get payment schedule details
+    pub fn get_schedule(env: Env, vault_id: u32, schedule_id: u64) -> Option<PaymentSchedule> {
+        env.load(&(vault_id, DataKey::Schedule(schedule_id)))
+    }
+    
+    // ==================== EPHEMERAL ACCOUNTS ====================
+    
+    /// Bind a freshly derived HSM key to a pending hot wallet transaction
+    pub fn register_ephemeral_account(
+        env: Env,
+        vault_id: u32,
+        tx_id: u64,
+        address: Address,
+        derivation_index: u32,
+        fee_buffer: i128,
+        expires_at: u64,
+    ) {
+        Self::check_initialized(&env, vault_id);
+        Self::check_operation_allowed(&env, vault_id, &Operation::Payments);
+        
+        // Ephemeral keys are derived from the hot wallet seed
+        let hot_wallet: Address = env.load(&(vault_id, DataKey::HotWallet)).unwrap();
+        hot_wallet.require_auth();
+        
+        let transaction: Transaction = env.load(&(vault_id, DataKey::Transaction(tx_id)))
+            .unwrap_or_else(|| panic!("Transaction not found"));
+        
+        if transaction.from_wallet != hot_wallet {
+            panic!("Only hot wallet transactions use ephemeral accounts");
+        }
+        if !matches!(transaction.status, TxStatus::AwaitingApproval) {
+            panic!("Transaction not awaiting approval");
+        }
+        if fee_buffer < 0 {
+            panic!("Fee buffer cannot be negative");
+        }
+        if expires_at <= env.ledger().timestamp() {
+            panic!("Expiry must be in the future");
+        }
+        
+        let ephemeral_key = (vault_id, DataKey::Ephemeral(tx_id));
+        if env.exists(&ephemeral_key) {
+            panic!("Ephemeral account already registered");
+        }
+        
+        // A derived key is single use, even once its transaction is done
+        let address_key = (vault_id, DataKey::EphemeralAddress(address.clone()));
+        let index_key = (vault_id, DataKey::DerivationIndex(derivation_index));
+        if env.exists(&address_key) {
+            panic!("Ephemeral address already used");
+        }
+        if env.exists(&index_key) {
+            panic!("Derivation index already used");
+        }
+        env.save(&address_key, &tx_id);
+        env.save(&index_key, &tx_id);
+        
+        let ephemeral = EphemeralAccount {
+            address,
+            tx_id,
+            derivation_index,
+            fee_buffer,
+            funded_amount: 0,
+            swept_amount: 0,
+            status: EphemeralStatus::Registered,
+            expires_at,
+        };
+        env.save(&ephemeral_key, &ephemeral);
+        
+        log!(&env, "Ephemeral account {} registered for transaction {}", derivation_index, tx_id);
+    }
+    
+    /// Sweep an ephemeral account's leftover to the hot wallet
+    pub fn sweep_ephemeral_account(env: Env, vault_id: u32, tx_id: u64, leftover: i128) {
+        Self::check_initialized(&env, vault_id);
+        Self::check_operation_allowed(&env, vault_id, &Operation::Deposits);
+        
+        let hot_wallet: Address = env.load(&(vault_id, DataKey::HotWallet)).unwrap();
+        hot_wallet.require_auth();
+        
+        let ephemeral_key = (vault_id, DataKey::Ephemeral(tx_id));
+        let mut ephemeral: EphemeralAccount = env.load(&ephemeral_key)
+            .unwrap_or_else(|| panic!("Ephemeral account not found"));
+        
+        if !matches!(ephemeral.status, EphemeralStatus::Funded) {
+            panic!("Ephemeral account not funded");
+        }
+        if leftover < 0 || leftover > ephemeral.fee_buffer {
+            panic!("Invalid leftover amount");
+        }
+        
+        let hot_wallet_key = (vault_id, DataKey::WalletInfo(hot_wallet));
+        let mut hot_wallet_info: WalletInfo = env.load(&hot_wallet_key).unwrap();
+        hot_wallet_info.balance += leftover;
+        env.save(&hot_wallet_key, &hot_wallet_info);
+        
+        ephemeral.swept_amount = leftover;
+        ephemeral.status = EphemeralStatus::Swept;
+        env.save(&ephemeral_key, &ephemeral);
+        
+        log!(&env, "Ephemeral account for transaction {} swept: {}", tx_id, leftover);
+    }
+    
+    /// Expire an unused ephemeral account (permissionless)
+    pub fn expire_ephemeral_account(env: Env, vault_id: u32, tx_id: u64) {
+        let ephemeral_key = (vault_id, DataKey::Ephemeral(tx_id));
+        let mut ephemeral: EphemeralAccount = env.load(&ephemeral_key)
+            .unwrap_or_else(|| panic!("Ephemeral account not found"));
+        
+        if !matches!(ephemeral.status, EphemeralStatus::Registered) {
+            panic!("Ephemeral account already used");
+        }
+        if env.ledger().timestamp() <= ephemeral.expires_at {
+            panic!("Ephemeral account not expired");
+        }
+        
+        ephemeral.status = EphemeralStatus::Expired;
+        env.save(&ephemeral_key, &ephemeral);
+        
+        log!(&env, "Ephemeral account for transaction {} expired", tx_id);
+    }
+    
+    /// Get the ephemeral account bound to a transaction
+    pub fn get_ephemeral_account(env: Env, vault_id: u32, tx_id: u64) -> Option<EphemeralAccount> {
+        env.load(&(vault_id, DataKey::Ephemeral(tx_id)))
+    }
+    
+    // ==================== COMPLIANCE ====================
+    
+    /// Propose the compliance officer (guardian quorum)
+    pub fn propose_compliance_officer(env: Env, vault_id: u32, guardian: Address, officer: Address) -> u64 {
+        guardian.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_acting_guardian(&env, vault_id, &guardian);
+        
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        Self::create_proposal(&env, vault_id, guardian, ProposalAction::SetComplianceOfficer(officer), system_limits.required_approvals)
+    }
+    
+    /// Get the current compliance officer
+    pub fn get_compliance_officer(env: Env, vault_id: u32) -> Option<Address> {
+        env.load(&(vault_id, DataKey::ComplianceOfficer))
+    }
+    
+    /// Add addresses to the denylist
+    pub fn add_to_denylist(env: Env, vault_id: u32, officer: Address, addresses: Vec<Address>) {
+        officer.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_compliance_officer(&env, vault_id, &officer);
+        
+        for address in addresses.iter() {
+            env.save(&(vault_id, DataKey::Denylisted(address)), &true);
+        }
+        
+        log!(&env, "{} addresses added to denylist", addresses.len());
+    }
+    
+    /// Remove addresses from the denylist
+    pub fn remove_from_denylist(env: Env, vault_id: u32, officer: Address, addresses: Vec<Address>) {
+        officer.require_auth();
+        
+        Self::check_initialized(&env, vault_id);
+        Self::require_compliance_officer(&env, vault_id, &officer);
+        
+        for address in addresses.iter() {
+            env.storage().persistent().remove(&(vault_id, DataKey::Denylisted(address)));
+        }
+        
+        log!(&env, "{} addresses removed from denylist", addresses.len());
+    }
+    
+    /// Check if an address is denylisted
+    pub fn is_denylisted(env: Env, vault_id: u32, address: Address) -> bool {
+        env.load(&(vault_id, DataKey::Denylisted(address))).unwrap_or(false)
+    }
+    
+    // ==================== INTERNAL FUNCTIONS ====================
+    
+    fn create_transaction_internal(env: &Env, vault_id: u32, request: NewTransaction) -> u64 {
+        let NewTransaction { from_wallet, to_address, amount, memo, tx_type, legs, idempotency_key } = request;
+        
+        Self::check_initialized(env, vault_id);
+        Self::check_operation_allowed(env, vault_id, &Self::operation_for(&tx_type));
+        
+        // Validate amount and memo
+        if amount <= 0 {
+            panic!("Amount must be positive");
+        }
+        Self::check_memo(&memo);
+        
+        // Screen destination against the compliance denylist
+        Self::check_not_denylisted(env, vault_id, &to_address);
+        
+        // Get wallet info and check balance
+        let wallet_key = (vault_id, DataKey::WalletInfo(from_wallet.clone()));
+        let mut wallet_info: WalletInfo = env.load(&wallet_key)
+            .unwrap_or_else(|| panic!("Wallet not found"));
+        
+        if !wallet_info.is_active {
+            panic!("Wallet not active");
+        }
+        if matches!(wallet_info.wallet_type, WalletType::Cold) {
+            Self::check_operation_allowed(env, vault_id, &Operation::ColdWallet);
+        }
+            
+        // The custody fee is quoted now and reserved with the amount, so execution cannot fall short
+        let fee = Self::transaction_fee(env, vault_id, &wallet_info, &tx_type, amount);
+        if wallet_info.balance - wallet_info.reserved_balance < amount + fee {
+            panic!("Insufficient balance");
+        }
+        
+        // Limits are in the reference currency when an oracle is configured
+        let value = Self::reference_value(env, vault_id, amount);
+        
+        // Per-destination velocity caps, counted once executed and checked again then
+        if !Self::destination_velocity(env, vault_id, &Self::destination_payments(env, &to_address, amount, value, &legs), false) {
+            panic!("Exceeds destination limit");
+        }
+        
+        // Named sub-accounts carry their own threshold and limits
+        let policy: Option<WalletPolicy> = env.load(&(vault_id, DataKey::WalletPolicy(from_wallet.clone())));
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        let high_value_threshold = policy.as_ref()
+            .map(|p| p.high_value_threshold)
+            .unwrap_or(system_limits.high_value_threshold);
+        if let (Some(policy), Some(value)) = (&policy, value) {
+            Self::check_wallet_limits(env, vault_id, &from_wallet, policy, value);
+        }
+        
+        // Check if requires approval, unpriced transactions always do
+        let requires_approval = value.is_none_or(|value| value > high_value_threshold) || 
+                               matches!(wallet_info.wallet_type, WalletType::Cold);
+        
+        // Check spending limits if requires approval
+        if let (true, Some(value)) = (requires_approval, value) {
+            Self::check_spending_limits(env, vault_id, value);
+        }
+        
+        // Get transaction counter and increment
+        let counter: u64 = env.load(&(vault_id, DataKey::TransactionCounter)).unwrap_or(0);
+        let tx_id = counter + 1;
+        
+        // Create transaction
+        let transaction = Transaction {
+            id: tx_id,
+            from_wallet: from_wallet.clone(),
+            to_address,
+            amount,
+            memo,
+            tx_type,
+            status: if requires_approval { TxStatus::AwaitingApproval } else { TxStatus::Pending },
+            approvals: Vec::new(env),
+            approval_weight: 0,
+            value,
+            fee,
+            idempotency_key: idempotency_key.clone(),
+            created_at: env.ledger().timestamp(),
+            executed_at: None,
+            requires_approval,
+        };
+        
+        // Reserve balance
+        wallet_info.reserved_balance += amount + fee;
+        env.save(&wallet_key, &wallet_info);
+        
+        // Store transaction
+        env.save(&(vault_id, DataKey::Transaction(tx_id)), &transaction);
+        env.save(&(vault_id, DataKey::TransactionCounter), &tx_id);
+        if let Some(idempotency_key) = idempotency_key {
+            env.save(&(vault_id, DataKey::IdempotencyKey(idempotency_key)), &tx_id);
+        }
+        
+        Self::index_status_add(env, vault_id, tx_id, &transaction.status);
+        Self::index_push(env, vault_id, TxFilter::Wallet(from_wallet.clone()), tx_id);
+        if let Some(legs) = legs {
+            // Index each distinct leg destination once, the batch record itself points at the contract
+            let mut destinations: Vec<Address> = Vec::new(env);
+            for leg in legs.iter() {
+                if !destinations.contains(&leg.to_address) {
+                    Self::index_push(env, vault_id, TxFilter::Destination(leg.to_address.clone()), tx_id);
+                    destinations.push_back(leg.to_address);
+                }
+            }
+            env.save(&(vault_id, DataKey::BatchLegs(tx_id)), &legs);
+        } else {
+            Self::index_push(env, vault_id, TxFilter::Destination(transaction.to_address.clone()), tx_id);
+        }
+        
+        // Commit to a challenge guardians must answer when approving
+        if requires_approval {
+            let challenge = Self::challenge_commitment(env, vault_id, &transaction);
+            env.save(&(vault_id, DataKey::Challenge(tx_id)), &challenge);
+        }
+        
+        // If doesn't need approval, execute immediately
+        if !requires_approval {
+            if let Err(failure) = Self::execute_transaction_internal(env, vault_id, tx_id) {
+                Self::execution_failed(failure);
+            }
+        }
+        
+        log!(env, "Transaction {} created, requires_approval: {}", tx_id, requires_approval);
+        tx_id
+    }
+    
+    /// Record a guardian's vote and execute once quorum is reached
+    fn apply_approval(env: &Env, vault_id: u32, approval: Approval, tx_id: u64) -> ApprovalOutcome {
+        // Get transaction
+        let tx_key = (vault_id, DataKey::Transaction(tx_id));
+        let mut transaction: Transaction = match env.load(&tx_key) {
+            Some(transaction) => transaction,
+            None => return ApprovalOutcome::NotFound,
+        };
+        
+        // Check if transaction is in correct status
+        if !matches!(transaction.status, TxStatus::AwaitingApproval) {
+            return ApprovalOutcome::NotAwaitingApproval;
+        }
+        
+        Self::prune_approvals(env, vault_id, &mut transaction);
+        
+        // Check if already approved by this guardian, directly or through a delegate
+        if transaction.approvals.iter().any(|a| a.guardian == approval.guardian) {
+            return ApprovalOutcome::AlreadyApproved;
+        }
+        
+        // Add approval
+        let guardian = approval.guardian.clone();
+        transaction.approval_weight += Self::guardian_weight(env, vault_id, &guardian);
+        transaction.approvals.push_back(approval);
+        
+        // Check if we have enough approval weight
+        let has_enough_approvals = Self::approval_threshold_met(
+            env, vault_id, &transaction.from_wallet, transaction.value, transaction.approval_weight, transaction.approvals.len(),
+        );
+        
+        // An approval that cannot execute is reported without being recorded
+        if has_enough_approvals {
+            if let Err(failure) = Self::check_executable(env, vault_id, &transaction) {
+                return ApprovalOutcome::ExecutionFailed(failure);
+            }
+        }
+        Self::index_push(env, vault_id, TxFilter::Guardian(guardian), tx_id);
+        
+        if has_enough_approvals {
+            Self::index_status_change(env, vault_id, tx_id, &transaction.status, &TxStatus::Approved);
+            transaction.status = TxStatus::Approved;
+        }
+        
+        // Persist before executing so the executed state is not overwritten
+        env.save(&tx_key, &transaction);
+        
+        log!(env, "Transaction {} approved by guardian, approval weight: {}", 
+             tx_id, transaction.approval_weight);
+        
+        if has_enough_approvals {
+            if let Err(failure) = Self::execute_transaction_internal(env, vault_id, tx_id) {
+                Self::execution_failed(failure);
+            }
+            ApprovalOutcome::Executed
+        } else {
+            ApprovalOutcome::Recorded
+        }
+    }
+    
+    /// Re-check what may have changed since creation: emergency mode, pauses, the
+    /// denylist and the balance left for an ephemeral fee buffer
+    fn check_executable(env: &Env, vault_id: u32, transaction: &Transaction) -> Result<(), ExecutionFailure> {
+        if Self::is_emergency_mode(env.clone(), vault_id) {
+            return Err(ExecutionFailure::EmergencyMode);
+        }
+        if Self::is_operation_paused(env.clone(), vault_id, Self::operation_for(&transaction.tx_type)) {
+            return Err(ExecutionFailure::OperationPaused);
+        }
+        if Self::is_denylisted(env.clone(), vault_id, transaction.to_address.clone()) {
+            return Err(ExecutionFailure::Denylisted);
+        }
+        let legs: Option<Vec<BatchLeg>> = env.load(&(vault_id, DataKey::BatchLegs(transaction.id)));
+        if let Some(legs) = &legs {
+            if legs.iter().any(|leg| Self::is_denylisted(env.clone(), vault_id, leg.to_address)) {
+                return Err(ExecutionFailure::Denylisted);
+            }
+        }
+        
+        // Caps are checked against executed usage, pending transactions may together exceed them
+        let payments = Self::destination_payments(env, &transaction.to_address, transaction.amount, transaction.value, &legs);
+        if !Self::destination_velocity(env, vault_id, &payments, false) {
+            return Err(ExecutionFailure::DestinationLimit);
+        }
+        
+        let from_wallet: WalletInfo = env.load(&(vault_id, DataKey::WalletInfo(transaction.from_wallet.clone())))
+            .unwrap_or_else(|| panic!("Wallet not found"));
+        if matches!(from_wallet.wallet_type, WalletType::Cold)
+            && Self::is_operation_paused(env.clone(), vault_id, Operation::ColdWallet) {
+            return Err(ExecutionFailure::OperationPaused);
+        }
+        
+        // The transfer itself is covered by the reservation made at creation
+        let ephemeral: Option<EphemeralAccount> = env.load(&(vault_id, DataKey::Ephemeral(transaction.id)));
+        if let Some(ephemeral) = ephemeral {
+            if matches!(ephemeral.status, EphemeralStatus::Registered)
+                && env.ledger().timestamp() <= ephemeral.expires_at
+                && from_wallet.balance - from_wallet.reserved_balance < ephemeral.fee_buffer {
+                return Err(ExecutionFailure::InsufficientBalance);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Panic with the reason an approved transaction cannot execute
+    fn execution_failed(failure: ExecutionFailure) -> ! {
+        match failure {
+            ExecutionFailure::EmergencyMode => panic!("Emergency mode active"),
+            ExecutionFailure::OperationPaused => panic!("Operation paused"),
+            ExecutionFailure::Denylisted => panic!("Address is denylisted"),
+            ExecutionFailure::InsufficientBalance => panic!("Insufficient balance for ephemeral fee buffer"),
+            ExecutionFailure::DestinationLimit => panic!("Exceeds destination limit"),
+        }
+    }
+    
+    fn execute_transaction_internal(env: &Env, vault_id: u32, tx_id: u64) -> Result<(), ExecutionFailure> {
+        let tx_key = (vault_id, DataKey::Transaction(tx_id));
+        let mut transaction: Transaction = env.load(&tx_key)
+            .unwrap_or_else(|| panic!("Transaction not found"));
+        
+        // Everything that can refuse execution is checked before any write
+        Self::check_executable(env, vault_id, &transaction)?;
+        
+        // Batches settle every leg or none of them
+        let legs_key = (vault_id, DataKey::BatchLegs(tx_id));
+        let legs: Option<Vec<BatchLeg>> = env.load(&legs_key);
+        if let Some(legs) = &legs {
+            let mut settled_legs: Vec<BatchLeg> = Vec::new(env);
+            for mut leg in legs.iter() {
+                leg.status = TxStatus::Executed;
+                settled_legs.push_back(leg);
+            }
+            env.save(&legs_key, &settled_legs);
+        }
+        
+        // Update wallet balances
+        let from_wallet_key = (vault_id, DataKey::WalletInfo(transaction.from_wallet.clone()));
+        let mut from_wallet: WalletInfo = env.load(&from_wallet_key).unwrap();
+        
+        // Execute the transfer and charge the custody fee reserved at creation
+        from_wallet.balance -= transaction.amount + transaction.fee;
+        from_wallet.reserved_balance -= transaction.amount + transaction.fee;
+        if transaction.fee > 0 {
+            let fee_schedule = Self::get_fee_schedule(env.clone(), vault_id).unwrap();
+            Self::record_fees(env, vault_id, &fee_schedule.fee_wallet, transaction.fee, 0);
+        }
+        
+        // Fund the ephemeral source account, if one is still valid for this transaction
+        let ephemeral_key = (vault_id, DataKey::Ephemeral(tx_id));
+        let ephemeral: Option<EphemeralAccount> = env.load(&ephemeral_key);
+        if let Some(mut ephemeral) = ephemeral {
+            if matches!(ephemeral.status, EphemeralStatus::Registered) {
+                if env.ledger().timestamp() > ephemeral.expires_at {
+                    ephemeral.status = EphemeralStatus::Expired;
+                } else {
+                    from_wallet.balance -= ephemeral.fee_buffer;
+                    ephemeral.funded_amount = transaction.amount + ephemeral.fee_buffer;
+                    ephemeral.status = EphemeralStatus::Funded;
+                }
+                env.save(&ephemeral_key, &ephemeral);
+            }
+        }
+        
+        // Update transaction status
+        Self::index_status_change(env, vault_id, tx_id, &transaction.status, &TxStatus::Executed);
+        transaction.status = TxStatus::Executed;
+        transaction.executed_at = Some(env.ledger().timestamp());
+        
+        // Update spending tracking
+        // Unpriced transactions were approved by the maximum tier and are not tracked
+        if let Some(value) = transaction.value {
+            Self::update_spending_tracking(env, vault_id, &transaction.from_wallet, value);
+        }
+        Self::destination_velocity(
+            env, vault_id, &Self::destination_payments(env, &transaction.to_address, transaction.amount, transaction.value, &legs), true,
+        );
+        
+        // Store updates
+        env.save(&from_wallet_key, &from_wallet);
+        env.save(&tx_key, &transaction);
+        
+        // Announce every payment with its memo, batches once per leg
+        if let Some(legs) = legs {
+            for leg in legs.iter() {
+                TransactionExecuted {
+                    vault_id,
+                    tx_id,
+                    from_wallet: transaction.from_wallet.clone(),
+                    to_address: leg.to_address,
+                    amount: leg.amount,
+                    memo: leg.memo,
+                }.publish(env);
+            }
+        } else {
+            TransactionExecuted {
+                vault_id,
+                tx_id,
+                from_wallet: transaction.from_wallet.clone(),
+                to_address: transaction.to_address.clone(),
+                amount: transaction.amount,
+                memo: transaction.memo.clone(),
+            }.publish(env);
+        }
+        
+        log!(env, "Transaction {} executed successfully", tx_id);
+        Ok(())
+    }
+    
+    fn check_memo(memo: &Memo) {
+        if let Memo::Text(text) = memo {
+            if text.len() > MAX_MEMO_TEXT_LEN {
+                panic!("Memo text too long");
+            }
+        }
+    }
+    
+    fn check_initialized(env: &Env, vault_id: u32) {
+        if !env.exists(&(vault_id, DataKey::Initialized)) {
+            panic!("Contract not initialized");
+        }
+        env.storage().instance().extend_ttl(TTL_THRESHOLD, TTL_EXTEND_TO);
+    }
+    
+    fn check_emergency_mode(env: &Env, vault_id: u32) {
+        let emergency_mode: bool = env.load(&(vault_id, DataKey::EmergencyMode))
+            .unwrap_or(false);
+            
+        if emergency_mode {
+            panic!("Emergency mode active");
+        }
+    }
+    
+    /// Emergency mode blocks everything, pause switches block a single operation type
+    fn check_operation_allowed(env: &Env, vault_id: u32, operation: &Operation) {
+        Self::check_emergency_mode(env, vault_id);
+        
+        if Self::is_operation_paused(env.clone(), vault_id, operation.clone()) {
+            panic!("Operation paused");
+        }
+    }
+    
+    fn operation_for(tx_type: &TxType) -> Operation {
+        match tx_type {
+            TxType::Payment | TxType::Batch => Operation::Payments,
+            TxType::Withdrawal | TxType::Emergency => Operation::Withdrawals,
+            TxType::Rebalance => Operation::Rebalances,
+        }
+    }
+    
+    fn validate_guardian_set(guardians: &Vec<Guardian>) {
+        if guardians.len() != 3 {
+            panic!("Must have exactly 3 guardians");
+        }
+        for (i, guardian) in guardians.iter().enumerate() {
+            if guardians.iter().position(|g| g.address == guardian.address) != Some(i) {
+                panic!("Duplicate guardian");
+            }
+        }
+    }
+    
+    /// Approvers that are still active guardians; approvals of replaced or
+    /// deactivated guardians stop counting
+    fn active_approvers(env: &Env, vault_id: u32, approvers: &Vec<Address>) -> Vec<Address> {
+        let guardians: Map<Address, Guardian> = env.load(&(vault_id, DataKey::Guardians)).unwrap();
+        let mut active = Vec::new(env);
+        for approver in approvers.iter() {
+            if guardians.get(approver.clone()).is_some_and(|g| g.is_active) {
+                active.push_back(approver);
+            }
+        }
+        active
+    }
+    
+    /// Drop transaction approvals of guardians that are no longer active and re-tally the weight
+    fn prune_approvals(env: &Env, vault_id: u32, transaction: &mut Transaction) {
+        let guardians: Map<Address, Guardian> = env.load(&(vault_id, DataKey::Guardians)).unwrap();
+        let mut approvals = Vec::new(env);
+        let mut approval_weight = 0;
+        for approval in transaction.approvals.iter() {
+            if guardians.get(approval.guardian.clone()).is_some_and(|g| g.is_active) {
+                approval_weight += Self::guardian_weight(env, vault_id, &approval.guardian);
+                approvals.push_back(approval);
+            }
+        }
+        transaction.approvals = approvals;
+        transaction.approval_weight = approval_weight;
+    }
+    
+    /// Check an active guardian acting on the vault and record it as guardian activity,
+    /// which resets the dead-man switch and cancels a pending inheritance claim
+    fn require_acting_guardian(env: &Env, vault_id: u32, guardian: &Address) -> Guardian {
+        let guardian_info = Self::require_active_guardian(env, vault_id, guardian);
+        env.save(&(vault_id, DataKey::LastHeartbeat), &env.ledger().timestamp());
+        
+        let claim_key = (vault_id, DataKey::InheritanceClaim);
+        let claim: Option<InheritanceClaim> = env.load(&claim_key);
+        if let Some(mut claim) = claim {
+            if matches!(claim.status, InheritanceStatus::Pending) {
+                let cold_key = Self::cold_wallet_key(env, vault_id);
+                let mut cold_wallet: WalletInfo = env.load(&cold_key).unwrap();
+                cold_wallet.reserved_balance -= claim.amount;
+                env.save(&cold_key, &cold_wallet);
+                
+                claim.status = InheritanceStatus::Cancelled;
+                env.save(&claim_key, &claim);
+                
+                InheritanceCancelled {
+                    vault_id,
+                    guardian: guardian.clone(),
+                }.publish(env);
+            }
+        }
+        guardian_info
+    }
+    
+    fn require_active_guardian(env: &Env, vault_id: u32, guardian: &Address) -> Guardian {
+        let guardians: Map<Address, Guardian> = env.load(&(vault_id, DataKey::Guardians))
+            .unwrap_or_else(|| panic!("Contract not initialized"));
+        
+        let guardian_info = guardians.get(guardian.clone()).unwrap_or_else(|| panic!("Not a guardian"));
+        if !guardian_info.is_active {
+            panic!("Guardian not active");
+        }
+        
+        guardian_info
+    }
+    
+    fn index_len(env: &Env, vault_id: u32, index: &TxFilter) -> u32 {
+        env.load(&(vault_id, IndexKey::Len(index.clone()))).unwrap_or(0)
+    }
+    
+    /// Append a transaction to an index, returning its position
+    fn index_push(env: &Env, vault_id: u32, index: TxFilter, tx_id: u64) -> u32 {
+        let len = Self::index_len(env, vault_id, &index);
+        let page_key = (vault_id, IndexKey::Page(index.clone(), len / INDEX_PAGE_SIZE));
+        let mut page: Vec<u64> = env.load(&page_key).unwrap_or_else(|| Vec::new(env));
+        page.push_back(tx_id);
+        env.save(&page_key, &page);
+        env.save(&(vault_id, IndexKey::Len(index)), &(len + 1));
+        len
+    }
+    
+    /// Read up to `limit` ids of an index starting at position `start`
+    fn index_read(env: &Env, vault_id: u32, index: &TxFilter, start: u32, limit: u32) -> Vec<u64> {
+        let mut ids = Vec::new(env);
+        let end = start.saturating_add(limit).min(Self::index_len(env, vault_id, index));
+        let mut position = start;
+        while position < end {
+            let page: Vec<u64> = env.load(&(vault_id, IndexKey::Page(index.clone(), position / INDEX_PAGE_SIZE))).unwrap();
+            let offset = position % INDEX_PAGE_SIZE;
+            let count = (end - position).min(INDEX_PAGE_SIZE - offset);
+            ids.append(&page.slice(offset..offset + count));
+            position += count;
+        }
+        ids
+    }
+    
+    fn index_status_add(env: &Env, vault_id: u32, tx_id: u64, status: &TxStatus) {
+        let position = Self::index_push(env, vault_id, TxFilter::Status(status.clone()), tx_id);
+        env.save(&(vault_id, IndexKey::StatusSlot(tx_id)), &position);
+    }
+    
+    /// Remove a transaction from its status index by moving the last entry into its slot
+    fn index_status_remove(env: &Env, vault_id: u32, tx_id: u64, status: &TxStatus) {
+        let index = TxFilter::Status(status.clone());
+        let position: u32 = env.load(&(vault_id, IndexKey::StatusSlot(tx_id))).unwrap();
+        let last = Self::index_len(env, vault_id, &index) - 1;
+        
+        let last_key = (vault_id, IndexKey::Page(index.clone(), last / INDEX_PAGE_SIZE));
+        let mut last_page: Vec<u64> = env.load(&last_key).unwrap();
+        let moved = last_page.pop_back().unwrap();
+        if last_page.is_empty() {
+            env.storage().persistent().remove(&last_key);
+        } else {
+            env.save(&last_key, &last_page);
+        }
+        
+        if position != last {
+            let page_key = (vault_id, IndexKey::Page(index.clone(), position / INDEX_PAGE_SIZE));
+            let mut page: Vec<u64> = env.load(&page_key).unwrap();
+            page.set(position % INDEX_PAGE_SIZE, moved);
+            env.save(&page_key, &page);
+            env.save(&(vault_id, IndexKey::StatusSlot(moved)), &position);
+        }
+        env.save(&(vault_id, IndexKey::Len(index)), &last);
+    }
+    
+    /// Move a transaction between status indexes
+    fn index_status_change(env: &Env, vault_id: u32, tx_id: u64, from: &TxStatus, to: &TxStatus) {
+        if from != to {
+            Self::index_status_remove(env, vault_id, tx_id, from);
+            Self::index_status_add(env, vault_id, tx_id, to);
+        }
+    }
+    
+    /// Summarize the transactions of a page
+    fn summarize_page(env: &Env, vault_id: u32, ids: Vec<u64>, limit: u32, cursor: u32, has_more: bool) -> TxPage {
+        let mut items: Vec<TxSummary> = Vec::new(env);
+        for tx_id in ids.iter() {
+            let transaction: Option<Transaction> = env.load(&(vault_id, DataKey::Transaction(tx_id)));
+            if let Some(transaction) = transaction {
+                items.push_back(TxSummary {
+                    id: transaction.id,
+                    from_wallet: transaction.from_wallet,
+                    to_address: if matches!(transaction.tx_type, TxType::Batch) { None } else { Some(transaction.to_address) },
+                    amount: transaction.amount,
+                    tx_type: transaction.tx_type,
+                    status: transaction.status,
+                    approval_count: transaction.approvals.len(),
+                    created_at: transaction.created_at,
+                });
+            }
+        }
+        
+        TxPage {
+            items,
+            next_cursor: if has_more { Some(cursor.saturating_add(limit)) } else { None },
+        }
+    }
+    
+    fn save_guardian(env: &Env, vault_id: u32, guardian_info: Guardian) {
+        let mut guardians: Map<Address, Guardian> = env.load(&(vault_id, DataKey::Guardians))
+            .unwrap_or_else(|| panic!("Contract not initialized"));
+        
+        guardians.set(guardian_info.address.clone(), guardian_info);
+        env.save(&(vault_id, DataKey::Guardians), &guardians);
+    }
+    
+    /// Canonical approval payload: contract id, network id (SHA-256 of the network
+    /// passphrase), vault id, tx id, amount, destination, the transaction's signature nonce
+    /// and, when present, its challenge commitment
+    fn approval_payload(env: &Env, vault_id: u32, transaction: &Transaction) -> Bytes {
+        let nonce: u64 = env.load(&(vault_id, DataKey::SignatureNonce(transaction.id)))
+            .unwrap_or(0);
+        
+        let mut payload = env.current_contract_address().to_xdr(env);
+        payload.append(&env.ledger().network_id().into());
+        payload.extend_from_array(&vault_id.to_be_bytes());
+        payload.extend_from_array(&transaction.id.to_be_bytes());
+        payload.extend_from_array(&transaction.amount.to_be_bytes());
+        payload.append(&transaction.to_address.clone().to_xdr(env));
+        payload.extend_from_array(&nonce.to_be_bytes());
+        
+        let challenge: Option<BytesN<32>> = env.load(&(vault_id, DataKey::Challenge(transaction.id)));
+        if let Some(challenge) = challenge {
+            payload.append(&challenge.into());
+        }
+        payload
+    }
+    
+    /// Challenge commitment: SHA-256 over the transaction context and a fresh nonce
+    fn challenge_commitment(env: &Env, vault_id: u32, transaction: &Transaction) -> BytesN<32> {
+        let nonce: u64 = env.prng().gen();
+        
+        let mut context = env.current_contract_address().to_xdr(env);
+        context.extend_from_array(&vault_id.to_be_bytes());
+        context.extend_from_array(&transaction.id.to_be_bytes());
+        context.append(&transaction.from_wallet.clone().to_xdr(env));
+        context.append(&transaction.to_address.clone().to_xdr(env));
+        context.extend_from_array(&transaction.amount.to_be_bytes());
+        context.extend_from_array(&transaction.created_at.to_be_bytes());
+        context.extend_from_array(&nonce.to_be_bytes());
+        env.crypto().sha256(&context).to_bytes()
+    }
+    
+    /// The expected response is the signer's ed25519 signature over the challenge
+    /// message, made with the HSM key registered on-chain, so only the key holder can
+    /// answer. False without a registered key; traps on a signature that does not verify
+    fn check_challenge_response(env: &Env, vault_id: u32, tx_id: u64, public_key: Option<BytesN<32>>, response: &BytesN<64>) -> bool {
+        let challenge: BytesN<32> = match env.load(&(vault_id, DataKey::Challenge(tx_id))) {
+            Some(challenge) => challenge,
+            None => return true, // nothing to answer, eligibility is checked separately
+        };
+        let public_key = match public_key {
+            Some(public_key) => public_key,
+            None => return false,
+        };
+        
+        env.crypto().ed25519_verify(&public_key, &Self::challenge_message(env, &challenge), response);
+        true
+    }
+    
+    /// Challenge message signed by guardians, domain-separated from the auth
+    /// payloads the same keys sign for `__check_auth`
+    fn challenge_message(env: &Env, challenge: &BytesN<32>) -> Bytes {
+        let mut message = Bytes::from_slice(env, CHALLENGE_DOMAIN);
+        message.append(&challenge.clone().into());
+        message
+    }
+    
+    fn active_guardian_count(env: &Env, vault_id: u32) -> u32 {
+        let guardians: Map<Address, Guardian> = env.load(&(vault_id, DataKey::Guardians))
+            .unwrap_or_else(|| panic!("Contract not initialized"));
+        guardians.values().iter().filter(|g| g.is_active).count() as u32
+    }
+    
+    /// Approvals needed to install `new_limits`: every active guardian if any limit loosens
+    fn limits_update_approvals(env: &Env, vault_id: u32, new_limits: &SystemLimits) -> u32 {
+        let current: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        let loosens = new_limits.daily_limit > current.daily_limit
+            || new_limits.monthly_limit > current.monthly_limit
+            || new_limits.high_value_threshold > current.high_value_threshold
+            || new_limits.required_approvals < current.required_approvals
+            || new_limits.hot_wallet_percentage > current.hot_wallet_percentage;
+        if loosens { Self::active_guardian_count(env, vault_id) } else { current.required_approvals }
+    }
+    
+    fn validate_limits(limits: &SystemLimits, guardian_count: u32) {
+        if limits.cold_wallet_percentage + limits.hot_wallet_percentage != 100 {
+            panic!("Wallet percentages must equal 100%");
+        }
+        if limits.daily_limit <= 0 || limits.daily_limit > limits.monthly_limit {
+            panic!("Daily limit must be positive and not exceed monthly limit");
+        }
+        if limits.high_value_threshold <= 0 || limits.high_value_threshold > limits.daily_limit {
+            panic!("High value threshold must be positive and not exceed daily limit");
+        }
+        if limits.required_approvals == 0 || limits.required_approvals > guardian_count {
+            panic!("Required approvals must be between 1 and the guardian count");
+        }
+    }
+    
+    fn validate_policy(policy: &WalletPolicy, guardian_count: u32) {
+        if policy.daily_limit <= 0 || policy.daily_limit > policy.monthly_limit {
+            panic!("Daily limit must be positive and not exceed monthly limit");
+        }
+        if policy.high_value_threshold <= 0 || policy.high_value_threshold > policy.daily_limit {
+            panic!("High value threshold must be positive and not exceed daily limit");
+        }
+        if policy.required_approvals == 0 || policy.required_approvals > guardian_count {
+            panic!("Required approvals must be between 1 and the guardian count");
+        }
+    }
+    
+    /// Approvals needed to move funds out of a wallet: its own policy, or the system default
+    fn required_approvals_for(env: &Env, vault_id: u32, wallet: &Address) -> u32 {
+        let policy: Option<WalletPolicy> = env.load(&(vault_id, DataKey::WalletPolicy(wallet.clone())));
+        match policy {
+            Some(policy) => policy.required_approvals,
+            None => {
+                let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+                system_limits.required_approvals
+            }
+        }
+    }
+    
+    fn validate_recovery_config(env: &Env, vault_id: u32, config: &RecoveryConfig) {
+        if config.threshold == 0 || config.threshold > config.guardians.len() {
+            panic!("Recovery threshold must be between 1 and the recovery guardian count");
+        }
+        if config.timelock < MIN_RECOVERY_TIMELOCK {
+            panic!("Recovery timelock too short");
+        }
+        let guardians: Map<Address, Guardian> = env.load(&(vault_id, DataKey::Guardians)).unwrap();
+        for (i, address) in config.guardians.iter().enumerate() {
+            if guardians.contains_key(address.clone()) {
+                panic!("Recovery guardians must not be operational guardians");
+            }
+            if config.guardians.first_index_of(&address) != Some(i as u32) {
+                panic!("Duplicate recovery guardian");
+            }
+        }
+    }
+    
+    fn hot_wallet_key(env: &Env, vault_id: u32) -> (u32, DataKey) {
+        let hot_wallet: Address = env.load(&(vault_id, DataKey::HotWallet)).unwrap();
+        (vault_id, DataKey::WalletInfo(hot_wallet))
+    }
+    
+    fn cold_wallet_key(env: &Env, vault_id: u32) -> (u32, DataKey) {
+        let cold_wallet: Address = env.load(&(vault_id, DataKey::ColdWallet)).unwrap();
+        (vault_id, DataKey::WalletInfo(cold_wallet))
+    }
+    
+    fn require_recovery_guardian(env: &Env, vault_id: u32, address: &Address) -> RecoveryConfig {
+        let config: RecoveryConfig = env.load(&(vault_id, DataKey::RecoveryConfig))
+            .unwrap_or_else(|| panic!("Recovery not configured"));
+        if !config.guardians.contains(address) {
+            panic!("Not a recovery guardian");
+        }
+        config
+    }
+    
+    fn pending_recovery(env: &Env, recovery_key: &(u32, DataKey)) -> RecoveryRequest {
+        let recovery: RecoveryRequest = env.load(recovery_key)
+            .unwrap_or_else(|| panic!("Recovery not found"));
+        if !matches!(recovery.status, RecoveryStatus::Pending) {
+            panic!("Recovery not pending");
+        }
+        recovery
+    }
+    
+    /// Start the timelock once the recovery threshold is reached
+    fn schedule_recovery_if_ready(env: &Env, vault_id: u32, config: &RecoveryConfig, recovery: &mut RecoveryRequest) {
+        if recovery.executable_at.is_some() || recovery.approvals.len() < config.threshold {
+            return;
+        }
+        
+        let executable_at = env.ledger().timestamp() + config.timelock;
+        recovery.executable_at = Some(executable_at);
+        
+        RecoveryScheduled {
+            vault_id,
+            recovery_id: recovery.id,
+            executable_at,
+        }.publish(env);
+    }
+    
+    fn validate_voting_policy(env: &Env, vault_id: u32, policy: &VotingPolicy) {
+        let guardians: Map<Address, Guardian> = env.load(&(vault_id, DataKey::Guardians)).unwrap();
+        let mut weights: Map<Address, u32> = Map::new(env);
+        for entry in policy.weights.iter() {
+            if !guardians.contains_key(entry.guardian.clone()) {
+                panic!("Not a guardian");
+            }
+            if entry.weight == 0 {
+                panic!("Guardian weight must be positive");
+            }
+            if weights.contains_key(entry.guardian.clone()) {
+                panic!("Duplicate guardian weight");
+            }
+            weights.set(entry.guardian, entry.weight);
+        }
+        let active_weight: u32 = guardians.values().iter()
+            .filter(|g| g.is_active)
+            .map(|g| weights.get(g.address).unwrap_or(1))
+            .sum();
+        
+        if policy.tiers.is_empty() || policy.tiers.get(0).unwrap().min_amount != 0 {
+            panic!("First tier must start at 0");
+        }
+        let mut previous: Option<i128> = None;
+        for tier in policy.tiers.iter() {
+            if previous.is_some_and(|min_amount| tier.min_amount <= min_amount) {
+                panic!("Tiers must be ascending by min amount");
+            }
+            if tier.required_weight == 0 || tier.required_weight > active_weight {
+                panic!("Required weight must be between 1 and the active guardian weight");
+            }
+            previous = Some(tier.min_amount);
+        }
+    }
+    
+    fn guardian_weight(env: &Env, vault_id: u32, guardian: &Address) -> u32 {
+        let policy: Option<VotingPolicy> = env.load(&(vault_id, DataKey::VotingPolicy));
+        policy
+            .and_then(|p| p.weights.iter().find(|w| w.guardian == *guardian))
+            .map(|w| w.weight)
+            .unwrap_or(1)
+    }
+    
+    /// Vote weight needed to approve a value: the highest matching tier of the
+    /// voting policy, or the wallet's required approvals at one vote per guardian.
+    /// Unpriced values need the maximum tier (every active guardian without a policy)
+    fn required_weight_for(env: &Env, vault_id: u32, wallet: &Address, value: Option<i128>) -> u32 {
+        let policy: Option<VotingPolicy> = env.load(&(vault_id, DataKey::VotingPolicy));
+        match (policy, value) {
+            (Some(policy), Some(value)) => policy.tiers.iter()
+                .filter(|tier| tier.min_amount <= value)
+                .last()
+                .map(|tier| tier.required_weight)
+                .unwrap(),
+            (Some(policy), None) => policy.tiers.iter()
+                .map(|tier| tier.required_weight)
+                .max()
+                .unwrap(),
+            (None, Some(_)) => Self::required_approvals_for(env, vault_id, wallet),
+            (None, None) => Self::active_guardian_count(env, vault_id),
+        }
+    }
+    
+    /// Whether a schedule's approvals meet the approval tier of its installment amount
+    fn schedule_approved(env: &Env, vault_id: u32, schedule: &PaymentSchedule) -> bool {
+        let approval_weight: u32 = schedule.approvals.iter()
+            .map(|g| Self::guardian_weight(env, vault_id, &g))
+            .sum();
+        let value = Self::reference_value(env, vault_id, schedule.amount);
+        Self::approval_threshold_met(env, vault_id, &schedule.from_wallet, value, approval_weight, schedule.approvals.len())
+    }
+    
+    /// Whether approvals reach the required weight and, for a wallet with its own
+    /// policy, its required approvals; with a voting policy both must hold
+    fn approval_threshold_met(env: &Env, vault_id: u32, wallet: &Address, value: Option<i128>, weight: u32, count: u32) -> bool {
+        let wallet_policy: Option<WalletPolicy> = env.load(&(vault_id, DataKey::WalletPolicy(wallet.clone())));
+        let required_count = wallet_policy.map(|policy| policy.required_approvals).unwrap_or(0);
+        weight >= Self::required_weight_for(env, vault_id, wallet, value) && count >= required_count
+    }
+    
+    /// Value of an amount in the reference currency, scaled like asset amounts.
+    /// Without an oracle amounts are used as is; None if the price is missing or stale
+    fn reference_value(env: &Env, vault_id: u32, amount: i128) -> Option<i128> {
+        let config: OracleConfig = match env.load(&(vault_id, DataKey::OracleConfig)) {
+            Some(config) => config,
+            None => return Some(amount),
+        };
+        
+        // A failing oracle is treated like a missing price
+        let oracle = PriceOracleClient::new(env, &config.oracle);
+        let price = match oracle.try_lastprice(&config.asset) {
+            Ok(Ok(Some(price))) => price,
+            _ => return None,
+        };
+        let decimals = match oracle.try_decimals() {
+            Ok(Ok(decimals)) => decimals,
+            _ => return None,
+        };
+        
+        if price.price <= 0 || env.ledger().timestamp().saturating_sub(price.timestamp) > config.max_age {
+            log!(env, "Price unavailable or stale");
+            return None;
+        }
+        amount.checked_mul(price.price)?.checked_div(10i128.checked_pow(decimals)?)
+    }
+    
+    /// Install new limits, keeping the replaced ones in the history
+    fn replace_limits(env: &Env, vault_id: u32, new_limits: &SystemLimits, proposal_id: u64) {
+        let current: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        let mut history = Self::get_limits_history(env.clone(), vault_id);
+        history.push_back(LimitsRecord {
+            limits: current,
+            replaced_at: env.ledger().timestamp(),
+            proposal_id,
+        });
+        env.save(&(vault_id, DataKey::LimitsHistory), &history);
+        env.save(&(vault_id, DataKey::SystemLimits), new_limits);
+    }
+    
+    /// Store a new proposal with the proposer's approval already counted
+    fn create_proposal(env: &Env, vault_id: u32, proposer: Address, action: ProposalAction, required_approvals: u32) -> u64 {
+        let counter: u64 = env.load(&(vault_id, DataKey::ProposalCounter)).unwrap_or(0);
+        let proposal_id = counter + 1;
+        
+        let mut approvals = Vec::new(env);
+        approvals.push_back(proposer.clone());
+        
+        let mut proposal = Proposal {
+            id: proposal_id,
+            proposer,
+            action,
+            approvals,
+            required_approvals,
+            status: ProposalStatus::Pending,
+            created_at: env.ledger().timestamp(),
+            expires_at: env.ledger().timestamp() + PROPOSAL_LIFETIME,
+            executed_at: None,
+        };
+        Self::try_execute_proposal(env, vault_id, &mut proposal);
+        
+        env.save(&(vault_id, DataKey::Proposal(proposal_id)), &proposal);
+        env.save(&(vault_id, DataKey::ProposalCounter), &proposal_id);
+        
+        log!(env, "Proposal {} created, required approvals: {}", proposal_id, required_approvals);
+        proposal_id
+    }
+    
+    /// Apply the proposal's action if it has enough approvals
+    fn try_execute_proposal(env: &Env, vault_id: u32, proposal: &mut Proposal) -> bool {
+        // Classified against the limits in force now, which other proposals may have changed
+        if let ProposalAction::UpdateLimits(new_limits) = &proposal.action {
+            Self::validate_limits(new_limits, Self::active_guardian_count(env, vault_id));
+            proposal.required_approvals = Self::limits_update_approvals(env, vault_id, new_limits);
+        }
+        
+        if proposal.approvals.len() < proposal.required_approvals {
+            return false;
+        }
+        
+        match &proposal.action {
+            ProposalAction::UpdateLimits(new_limits) => {
+                Self::replace_limits(env, vault_id, new_limits, proposal.id);
+            }
+            ProposalAction::SetFeeSchedule(fee_schedule) => {
+                // Collect what accrued under the previous schedule before switching rates
+                if env.exists(&(vault_id, FeeKey::State)) {
+                    Self::collect_aum_fee(env.clone(), vault_id);
+                }
+                
+                // Re-checked at execution, a wallet proposal may have claimed the address
+                Self::check_fee_wallet(env, vault_id, &fee_schedule.fee_wallet);
+                let fee_wallet_key = (vault_id, DataKey::WalletInfo(fee_schedule.fee_wallet.clone()));
+                if !env.exists(&fee_wallet_key) {
+                    let wallet_info = WalletInfo {
+                        address: fee_schedule.fee_wallet.clone(),
+                        wallet_type: WalletType::Fee,
+                        balance: 0,
+                        reserved_balance: 0,
+                        is_active: true,
+                    };
+                    env.save(&fee_wallet_key, &wallet_info);
+                }
+                let fee_state = FeeState {
+                    schedule: fee_schedule.clone(),
+                    last_aum_collection: env.ledger().timestamp(),
+                };
+                env.save(&(vault_id, FeeKey::State), &fee_state);
+            }
+            ProposalAction::SetComplianceOfficer(officer) => {
+                env.save(&(vault_id, DataKey::ComplianceOfficer), officer);
+            }
+            ProposalAction::SetReferencePricing(pricing) => {
+                env.save(&(vault_id, DataKey::OracleConfig), &pricing.oracle);
+                Self::replace_limits(env, vault_id, &pricing.limits, proposal.id);
+            }
+            ProposalAction::Unpause(operation) => {
+                env.storage().persistent().remove(&(vault_id, DataKey::Paused(operation.clone())));
+            }
+            ProposalAction::CreateWallet(wallet) => {
+                // Re-checked at execution, another proposal may have claimed the name or address
+                if env.exists(&(vault_id, DataKey::NamedWallet(wallet.name.clone())))
+                    || env.exists(&(vault_id, DataKey::WalletInfo(wallet.address.clone()))) {
+                    panic!("Wallet already registered");
+                }
+                
+                let wallet_info = WalletInfo {
+                    address: wallet.address.clone(),
+                    wallet_type: wallet.wallet_type.clone(),
+                    balance: 0,
+                    reserved_balance: 0,
+                    is_active: true,
+                };
+                env.save(&(vault_id, DataKey::WalletInfo(wallet.address.clone())), &wallet_info);
+                env.save(&(vault_id, DataKey::WalletPolicy(wallet.address.clone())), &wallet.policy);
+                env.save(&(vault_id, DataKey::NamedWallet(wallet.name.clone())), wallet);
+                
+                let mut names: Vec<Symbol> = env.load(&(vault_id, DataKey::WalletNames)).unwrap_or_else(|| Vec::new(env));
+                names.push_back(wallet.name.clone());
+                env.save(&(vault_id, DataKey::WalletNames), &names);
+            }
+            ProposalAction::SetRecoveryConfig(config) => {
+                env.save(&(vault_id, DataKey::RecoveryConfig), config);
+            }
+            ProposalAction::SetDestinationLimit(update) => {
+                let key = Self::destination_limit_key(vault_id, &update.destination);
+                env.save(&key, &update.limit);
+            }
+            ProposalAction::RemoveDestinationLimit(destination) => {
+                env.storage().persistent().remove(&Self::destination_limit_key(vault_id, destination));
+            }
+            ProposalAction::SetVotingPolicy(policy) => {
+                env.save(&(vault_id, DataKey::VotingPolicy), policy);
+            }
+            ProposalAction::SetInheritance(config) => {
+                // Start the inactivity timer from activation
+                env.save(&(vault_id, DataKey::InheritanceConfig), config);
+                env.save(&(vault_id, DataKey::LastHeartbeat), &env.ledger().timestamp());
+            }
+            ProposalAction::RetireWallet(name) => {
+                let wallet: NamedWallet = env.load(&(vault_id, DataKey::NamedWallet(name.clone()))).unwrap();
+                let wallet_key = (vault_id, DataKey::WalletInfo(wallet.address));
+                let mut wallet_info: WalletInfo = env.load(&wallet_key).unwrap();
+                if wallet_info.balance != 0 || wallet_info.reserved_balance != 0 {
+                    panic!("Wallet must be empty to retire");
+                }
+                wallet_info.is_active = false;
+                env.save(&wallet_key, &wallet_info);
+            }
+        }
+        
+        proposal.status = ProposalStatus::Executed;
+        proposal.executed_at = Some(env.ledger().timestamp());
+        
+        log!(env, "Proposal {} executed", proposal.id);
+        true
+    }
+    
+    fn require_compliance_officer(env: &Env, vault_id: u32, officer: &Address) {
+        let current: Address = env.load(&(vault_id, DataKey::ComplianceOfficer))
+            .unwrap_or_else(|| panic!("Compliance officer not set"));
+        
+        if current != *officer {
+            panic!("Not the compliance officer");
+        }
+    }
+    
+    fn check_not_denylisted(env: &Env, vault_id: u32, address: &Address) {
+        if env.exists(&(vault_id, DataKey::Denylisted(address.clone()))) {
+            panic!("Address is denylisted");
+        }
+    }
+    
+    fn check_spending_limits(env: &Env, vault_id: u32, amount: i128) {
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        
+        // Check daily limit
+        let today = Self::current_day(env);
+        let daily_spent: i128 = env.load(&(vault_id, DataKey::DailySpent(today)))
+            .unwrap_or(0);
+            
+        if daily_spent + amount > system_limits.daily_limit {
+            panic!("Exceeds daily limit");
+        }
+        
+        // Check monthly limit
+        let current_month = today / 30; // Approximate month
+        let monthly_spent: i128 = env.load(&(vault_id, DataKey::MonthlySpent(current_month)))
+            .unwrap_or(0);
+            
+        if monthly_spent + amount > system_limits.monthly_limit {
+            panic!("Exceeds monthly limit");
+        }
+    }
+    
+    fn check_wallet_limits(env: &Env, vault_id: u32, wallet: &Address, policy: &WalletPolicy, amount: i128) {
+        let today = Self::current_day(env);
+        
+        let daily_spent: i128 = env.load(&(vault_id, DataKey::WalletDailySpent(wallet.clone(), today)))
+            .unwrap_or(0);
+        if daily_spent + amount > policy.daily_limit {
+            panic!("Exceeds wallet daily limit");
+        }
+        
+        let monthly_spent: i128 = env.load(&(vault_id, DataKey::WalletMonthlySpent(wallet.clone(), today / 30)))
+            .unwrap_or(0);
+        if monthly_spent + amount > policy.monthly_limit {
+            panic!("Exceeds wallet monthly limit");
+        }
+    }
+    
+    fn destination_limit_key(vault_id: u32, destination: &Option<Address>) -> (u32, DataKey) {
+        match destination {
+            Some(destination) => (vault_id, DataKey::DestinationLimit(destination.clone())),
+            None => (vault_id, DataKey::DefaultDestinationLimit),
+        }
+    }
+    
+    /// What a transaction pays each destination, batch legs valued in proportion to the transaction value.
+    /// Unpriced payments only count towards the transaction count caps
+    fn destination_payments(env: &Env, to_address: &Address, amount: i128, value: Option<i128>, legs: &Option<Vec<BatchLeg>>) -> Map<Address, DestinationUsage> {
+        let value = value.unwrap_or(0);
+        let mut payments: Map<Address, DestinationUsage> = Map::new(env);
+        match legs {
+            Some(legs) => {
+                for leg in legs.iter() {
+                    let mut payment = payments.get(leg.to_address.clone()).unwrap_or(DestinationUsage { amount: 0, count: 0 });
+                    payment.amount += leg.amount * value / amount;
+                    payment.count += 1;
+                    payments.set(leg.to_address, payment);
+                }
+            }
+            None => payments.set(to_address.clone(), DestinationUsage { amount: value, count: 1 }),
+        }
+        payments
+    }
+    
+    /// Whether payments stay within their destinations' velocity caps, or once executed count them
+    fn destination_velocity(env: &Env, vault_id: u32, payments: &Map<Address, DestinationUsage>, executed: bool) -> bool {
+        let today = Self::current_day(env);
+        for (destination, payment) in payments.iter() {
+            let mut daily = Self::get_destination_usage(env.clone(), vault_id, destination.clone(), PeriodKey::Day(today));
+            let mut monthly = Self::get_destination_usage(env.clone(), vault_id, destination.clone(), PeriodKey::Month(today / 30));
+            daily.amount += payment.amount;
+            daily.count += payment.count;
+            monthly.amount += payment.amount;
+            monthly.count += payment.count;
+            
+            if executed {
+                env.save(&(vault_id, DataKey::DestinationDaily(destination.clone(), today)), &daily);
+                env.save(&(vault_id, DataKey::DestinationMonthly(destination, today / 30)), &monthly);
+            } else if let Some(limit) = Self::get_destination_limit(env.clone(), vault_id, destination) {
+                if daily.amount > limit.daily_amount || monthly.amount > limit.monthly_amount
+                    || daily.count > limit.daily_count || monthly.count > limit.monthly_count {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+    
+    /// Credit collected fees to the fee wallet and add them to the fee ledger of the current day and month
+    fn record_fees(env: &Env, vault_id: u32, fee_wallet: &Address, transaction_fees: i128, aum_fees: i128) {
+        let fee_wallet_key = (vault_id, DataKey::WalletInfo(fee_wallet.clone()));
+        let mut fee_wallet_info: WalletInfo = env.load(&fee_wallet_key)
+            .unwrap_or_else(|| panic!("Fee wallet not found"));
+        fee_wallet_info.balance += transaction_fees + aum_fees;
+        env.save(&fee_wallet_key, &fee_wallet_info);
+        
+        let today = Self::current_day(env);
+        for period in [PeriodKey::Day(today), PeriodKey::Month(today / 30)] {
+            let mut entry = Self::get_fees(env.clone(), vault_id, period.clone());
+            entry.transaction_fees += transaction_fees;
+            entry.aum_fees += aum_fees;
+            env.save(&(vault_id, FeeKey::Ledger(period)), &entry);
+        }
+    }
+    
+    /// Custody fee on a transaction, rebalances between vault wallets and payouts of the fee wallet are exempt
+    fn transaction_fee(env: &Env, vault_id: u32, wallet_info: &WalletInfo, tx_type: &TxType, amount: i128) -> i128 {
+        let fee_schedule = Self::get_fee_schedule(env.clone(), vault_id);
+        let exempt = matches!(tx_type, TxType::Rebalance) || matches!(wallet_info.wallet_type, WalletType::Fee);
+        match (fee_schedule, exempt) {
+            (Some(fee_schedule), false) => fee_schedule.flat_fee + amount * fee_schedule.fee_bps as i128 / BPS_DENOMINATOR,
+            _ => 0,
+        }
+    }
+    
+    /// The fee wallet must not be one of the vault's own wallets
+    fn check_fee_wallet(env: &Env, vault_id: u32, fee_wallet: &Address) {
+        let wallet_info: Option<WalletInfo> = env.load(&(vault_id, DataKey::WalletInfo(fee_wallet.clone())));
+        if wallet_info.is_some_and(|info| !matches!(info.wallet_type, WalletType::Fee)) {
+            panic!("Fee wallet must not be a vault wallet");
+        }
+    }
+    
+    fn update_spending_tracking(env: &Env, vault_id: u32, wallet: &Address, amount: i128) {
+        let today = Self::current_day(env);
+        let current_month = today / 30;
+        
+        // Update daily spending
+        let daily_spent: i128 = env.load(&(vault_id, DataKey::DailySpent(today)))
+            .unwrap_or(0);
+        env.save(&(vault_id, DataKey::DailySpent(today)), &(daily_spent + amount));
+        
+        // Update monthly spending
+        let monthly_spent: i128 = env.load(&(vault_id, DataKey::MonthlySpent(current_month)))
+            .unwrap_or(0);
+        env.save(&(vault_id, DataKey::MonthlySpent(current_month)), &(monthly_spent + amount));
+        
+        // Update per-wallet spending
+        let wallet_daily_key = (vault_id, DataKey::WalletDailySpent(wallet.clone(), today));
+        let wallet_daily_spent: i128 = env.load(&wallet_daily_key).unwrap_or(0);
+        env.save(&wallet_daily_key, &(wallet_daily_spent + amount));
+        
+        let wallet_monthly_key = (vault_id, DataKey::WalletMonthlySpent(wallet.clone(), current_month));
+        let wallet_monthly_spent: i128 = env.load(&wallet_monthly_key).unwrap_or(0);
+        env.save(&wallet_monthly_key, &(wallet_monthly_spent + amount));
+    }
+    
+    fn current_day(env: &Env) -> u64 {
+        env.ledger().timestamp() / 86400 // Convert to days
+    }
+    
+    /// Check the calls a custom account signature authorizes against the vault's policy.
+    /// Returns the weight the signers need, and the value and amount transferred out of the account
+    fn check_auth_contexts(env: &Env, vault_id: u32, auth_contexts: &Vec<Context>) -> Result<(u32, i128, i128), AccountError> {
+        let account = env.current_contract_address();
+        
+        // Calls without an amount need the highest weight tier of a voting policy
+        let policy: Option<VotingPolicy> = env.load(&(vault_id, DataKey::VotingPolicy));
+        let system_limits: SystemLimits = env.load(&(vault_id, DataKey::SystemLimits)).unwrap();
+        let base_weight = match policy {
+            Some(policy) => policy.tiers.iter().map(|tier| tier.required_weight).max().unwrap(),
+            None => system_limits.required_approvals,
+        };
+        
+        let mut required_weight = if auth_contexts.is_empty() { base_weight } else { 0 };
+        let mut spent: i128 = 0;
+        let mut moved: i128 = 0;
+        let shared = Self::get_vault_count(env.clone()) > 1;
+        for context in auth_contexts.iter() {
+            // Deploying contracts on behalf of the vault is not supported
+            let call = match context {
+                Context::Contract(call) => call,
+                _ => return Err(AccountError::UnsupportedContext),
+            };
+            
+            // Calls into this contract name their vault first, a vault's guardians only act for their own vault
+            if call.contract == account {
+                let named = call.args.get(0).and_then(|arg| u32::try_from_val(env, &arg).ok());
+                if named != Some(vault_id) {
+                    return Err(AccountError::VaultMismatch);
+                }
+                required_weight = required_weight.max(base_weight);
+                continue;
+            }
+            
+            // Calls into other contracts can move the vault's assets
+            if Self::is_operation_paused(env.clone(), vault_id, Operation::Payments) {
+                return Err(AccountError::OperationPaused);
+            }
+            for arg in call.args.iter() {
+                if let Ok(address) = Address::try_from_val(env, &arg) {
+                    if env.exists(&(vault_id, DataKey::Denylisted(address))) {
+                        return Err(AccountError::Denylisted);
+                    }
+                }
+            }
+            
+            // Transfers of the vault's asset are valued, any other call needs the weight of an unpriced value.
+            // The contract's holdings belong to every vault, so with several vaults only
+            // transfers covered by the vault's hot wallet can be authorized
+            let value = match Self::transferred_amount(env, vault_id, &call, shared) {
+                Some(amount) => {
+                    moved += amount;
+                    Self::reference_value(env, vault_id, amount)
+                }
+                None if shared => return Err(AccountError::UnsupportedContext),
+                None => None,
+            };
+            if let Some(value) = value {
+                spent += value;
+            }
+            required_weight = required_weight.max(Self::required_weight_for(env, vault_id, &account, value));
+        }
+        
+        if spent > 0 {
+            let today = Self::current_day(env);
+            let daily_spent = Self::get_period_spending(env.clone(), vault_id, PeriodKey::Day(today), None);
+            let monthly_spent = Self::get_period_spending(env.clone(), vault_id, PeriodKey::Month(today / 30), None);
+            if daily_spent + spent > system_limits.daily_limit || monthly_spent + spent > system_limits.monthly_limit {
+                return Err(AccountError::LimitExceeded);
+            }
+        }
+        
+        // Transfers are paid from the hot wallet, the contract's token balance is not the vault's alone
+        if moved > 0 {
+            let hot_wallet: WalletInfo = env.load(&Self::hot_wallet_key(env, vault_id)).unwrap();
+            if hot_wallet.balance - hot_wallet.reserved_balance < moved {
+                return Err(AccountError::InsufficientBalance);
+            }
+        }
+        
+        Ok((required_weight, spent, moved))
+    }
+    
+    /// Amount of a token `transfer(from, to, amount)` out of the account, for the vault's
+    /// asset when priced by an oracle. A shared contract only knows a vault's asset from its oracle
+    fn transferred_amount(env: &Env, vault_id: u32, call: &ContractContext, shared: bool) -> Option<i128> {
+        if call.fn_name != Symbol::new(env, "transfer") || call.args.len() != 3 {
+            return None;
+        }
+        let oracle: Option<OracleConfig> = env.load(&(vault_id, DataKey::OracleConfig));
+        match oracle {
+            Some(config) if config.asset != Asset::Stellar(call.contract.clone()) => return None,
+            None if shared => return None,
+            _ => {}
+        }
+        
+        let from = Address::try_from_val(env, &call.args.get(0)?).ok()?;
+        if from != env.current_contract_address() {
+            return None;
+        }
+        i128::try_from_val(env, &call.args.get(2)?).ok().filter(|amount| *amount >= 0)
+    }
+}
+
+// ==================== CUSTOM ACCOUNT ====================
+
+/// Lets the contract address be used anywhere a Stellar account is expected:
+/// `custody.require_auth()` succeeds only with ed25519 signatures over the
+/// auth payload from a quorum of the active guardians of the signing vault.
+#[contractimpl]
+impl CustomAccountInterface for CustodyContract {
+    type Signature = AccountSignature;
+    type Error = AccountError;
+    
+    fn __check_auth(
+        env: Env,
+        signature_payload: Hash<32>,
+        signature: AccountSignature,
+        auth_contexts: Vec<Context>,
+    ) -> Result<(), AccountError> {
+        let AccountSignature { vault_id, signatures } = signature;
+        if !env.exists(&(vault_id, DataKey::Initialized)) {
+            return Err(AccountError::NotInitialized);
+        }
+        
+        if Self::is_emergency_mode(env.clone(), vault_id) {
+            return Err(AccountError::EmergencyMode);
+        }
+        let (required_weight, spent, moved) = Self::check_auth_contexts(&env, vault_id, &auth_contexts)?;
+        
+        // Every signer is checked before any signature is verified, only a forged signature traps
+        let guardians: Map<Address, Guardian> = env.load(&(vault_id, DataKey::Guardians))
+            .ok_or(AccountError::NotInitialized)?;
+        let mut signers: Vec<Address> = Vec::new(&env);
+        let mut public_keys: Vec<BytesN<32>> = Vec::new(&env);
+        let mut weight: u32 = 0;
+        for entry in signatures.iter() {
+            if signers.contains(&entry.guardian) {
+                return Err(AccountError::DuplicateSigner);
+            }
+            
+            let guardian_info = guardians.get(entry.guardian.clone()).ok_or(AccountError::NotAGuardian)?;
+            if !guardian_info.is_active {
+                return Err(AccountError::GuardianNotActive);
+            }
+            
+            let public_key: BytesN<32> = env.load(&(vault_id, DataKey::GuardianKey(entry.guardian.clone())))
+                .ok_or(AccountError::KeyNotRegistered)?;
+            public_keys.push_back(public_key);
+            
+            // Signers are weighed like transaction approvals
+            weight += Self::guardian_weight(&env, vault_id, &entry.guardian);
+            signers.push_back(entry.guardian);
+        }
+        if weight < required_weight {
+            return Err(AccountError::InsufficientSignatures);
+        }
+        
+        let message: Bytes = signature_payload.to_bytes().into();
+        for (entry, public_key) in signatures.iter().zip(public_keys.iter()) {
+            env.crypto().ed25519_verify(&public_key, &message, &entry.signature);
+        }
+        
+        if spent > 0 {
+            Self::update_spending_tracking(&env, vault_id, &env.current_contract_address(), spent);
+        }
+        if moved > 0 {
+            let key = Self::hot_wallet_key(&env, vault_id);
+            let mut hot_wallet: WalletInfo = env.load(&key).unwrap();
+            hot_wallet.balance -= moved;
+            env.save(&key, &hot_wallet);
+        }
+        env.save(&(vault_id, DataKey::LastHeartbeat), &env.ledger().timestamp());
+        
+        Ok(())
+    }
+}
+
+// ==================== TESTS ====================
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    
+    use super::*;
+    use soroban_sdk::{auth::{ContractExecutable, CreateContractHostFnContext}, symbol_short, testutils::{storage::{Instance as _, Persistent as _}, Address as _, Events, Ledger}, Env, IntoVal, Vec};
+    use ed25519_dalek::{Signer, SigningKey};
+    
+    fn create_test_guardians(env: &Env) -> Vec<Guardian> {
+        let mut guardians = Vec::new(env);
+        guardians.push_back(Guardian {
+            address: Address::generate(env),
+            role: Symbol::new(env, "CEO"),
+            is_active: true,
+            daily_limit: 100000,
+            monthly_limit: 1000000,
+            approval_count: 0,
+            last_approval: 0,
+        });
+        guardians.push_back(Guardian {
+            address: Address::generate(env),
+            role: Symbol::new(env, "CFO"),
+            is_active: true,
+            daily_limit: 100000,
+            monthly_limit: 1000000,
+            approval_count: 0,
+            last_approval: 0,
+        });
+        guardians.push_back(Guardian {
+            address: Address::generate(env),
+            role: Symbol::new(env, "CTO"),
+            is_active: true,
+            daily_limit: 100000,
+            monthly_limit: 1000000,
+            approval_count: 0,
+            last_approval: 0,
+        });
+        guardians
+    }
+    
+    fn create_test_system_limits() -> SystemLimits {
+        SystemLimits {
+            daily_limit: 100000,
+            monthly_limit: 1000000,
+            high_value_threshold: 1000,
+            required_approvals: 2,
+            cold_wallet_percentage: 95,
+            hot_wallet_percentage: 5,
+        }
+    }
+    
+    fn setup_contract(env: &Env) -> (Address, u32, Vec<Guardian>, Address, Address) {
+        let contract_id = env.register(CustodyContract, (Address::generate(env),));
+        let client = CustodyContractClient::new(env, &contract_id);
+        
+        let guardians = create_test_guardians(env);
+        let hot_wallet = Address::generate(env);
+        let cold_wallet = Address::generate(env);
+        
+        let vault_id = client.create_vault(
+            &guardians,
+            &hot_wallet,
+            &cold_wallet,
+            &create_test_system_limits(),
+        );
+        
+        (contract_id, vault_id, guardians, hot_wallet, cold_wallet)
+    }
+    
+    /// SEP-40 oracle stand-in with 14 decimals
+    #[contract]
+    pub struct MockOracle;
+    
+    #[contractimpl]
+    impl MockOracle {
+        pub fn set_price(env: Env, price: i128, timestamp: u64) {
+            env.storage().instance().set(&symbol_short!("price"), &PriceData { price, timestamp });
+        }
+        
+        pub fn lastprice(env: Env, _asset: Asset) -> Option<PriceData> {
+            env.storage().instance().get(&symbol_short!("price"))
+        }
+        
+        pub fn decimals(_env: Env) -> u32 {
+            14
+        }
+    }
+    
+    /// Deterministic stand-in for a signer's HSM key
+    fn signing_key(env: &Env, signer: &Address) -> SigningKey {
+        SigningKey::from_bytes(&env.crypto().sha256(&signer.clone().to_xdr(env)).to_array())
+    }
+    
+    fn public_key(env: &Env, signer: &Address) -> BytesN<32> {
+        BytesN::from_array(env, &signing_key(env, signer).verifying_key().to_bytes())
+    }
+    
+    /// Sign the transaction's challenge, registering the signer's key first if it is a guardian without one
+    fn challenge_response(env: &Env, client: &CustodyContractClient, vault_id: u32, tx_id: u64, signer: &Address) -> BytesN<64> {
+        if client.get_guardian(&vault_id, signer).is_some() && client.get_guardian_key(&vault_id, signer).is_none() {
+            client.register_guardian_key(&vault_id, signer, &public_key(env, signer));
+        }
+        let mut message: std::vec::Vec<u8> = CHALLENGE_DOMAIN.into();
+        message.extend(client.get_challenge(&vault_id, &tx_id).unwrap().to_array());
+        BytesN::from_array(env, &signing_key(env, signer).sign(&message).to_bytes())
+    }
+    
+    fn fund_wallet(env: &Env, contract_id: &Address, vault_id: u32, wallet: &Address, wallet_type: WalletType, balance: i128) {
+        let wallet_info = WalletInfo {
+            address: wallet.clone(),
+            wallet_type,
+            balance,
+            reserved_balance: 0,
+            is_active: true,
+        };
+        env.as_contract(contract_id, || {
+            env.storage().persistent().set(&(vault_id, DataKey::WalletInfo(wallet.clone())), &wallet_info);
+        });
+    }
+    
+    #[test]
+    fn test_initialize_contract() {
+        let env = Env::default();
+        let admin = Address::generate(&env);
+        let contract_id = env.register(CustodyContract, (admin.clone(),));
+        let client = CustodyContractClient::new(&env, &contract_id);
+        
+        let guardians = create_test_guardians(&env);
+        let hot_wallet = Address::generate(&env);
+        let cold_wallet = Address::generate(&env);
+        let system_limits = create_test_system_limits();
+        
+        // Only the deployment admin can create vaults
+        assert_eq!(client.get_admin(), admin);
+        assert!(client.try_create_vault(&guardians, &hot_wallet, &cold_wallet, &system_limits).is_err());
+        env.mock_all_auths();
+        
+        let vault_id = client.create_vault(
+            &guardians,
+            &hot_wallet,
+            &cold_wallet,
+            &system_limits,
+        );
+        
+        // Test that contract is initialized
+        let first_guardian = guardians.get(0).unwrap();
+        let guardian_count = client.get_guardian(&vault_id, &first_guardian.address);
+        assert!(guardian_count.is_some());
+    }
+    
+    #[test]
+    fn test_create_transaction() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register(CustodyContract, (Address::generate(&env),));
+        let client = CustodyContractClient::new(&env, &contract_id);
+        
+        let guardians = create_test_guardians(&env);
+        let hot_wallet = Address::generate(&env);
+        let cold_wallet = Address::generate(&env);
+        let system_limits = create_test_system_limits();
+        
+        // Initialize
+        let vault_id = client.create_vault(
+            &guardians,
+            &hot_wallet,
+            &cold_wallet,
+            &system_limits,
+        );
+        
+        // Add some balance to hot wallet for testing (simulate funding)
+        let hot_wallet_key = (vault_id, DataKey::WalletInfo(hot_wallet.clone()));
+        let hot_wallet_info = WalletInfo {
+            address: hot_wallet.clone(),
+            wallet_type: WalletType::Hot,
+            balance: 10000, // Add sufficient balance
+            reserved_balance: 0,
+            is_active: true,
+        };
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().set(&hot_wallet_key, &hot_wallet_info);
+        });
+        
+        // Create transaction
+        let to_address = Address::generate(&env);
+        let amount = 500i128; // Below threshold
+        let memo = Memo::Text(String::from_str(&env, "test_tx"));
+        
+        let tx_id = client.create_transaction(
+            &vault_id,
+            &hot_wallet,
+            &to_address,
+            &amount,
+            &memo,
+            &TxType::Payment,
+        );
+        
+        assert_eq!(tx_id, 1);
+        
+        // Check transaction exists
+        let transaction = client.get_transaction(&vault_id, &tx_id);
+        assert!(transaction.is_some());
+    }
+    
+    #[test]
+    fn test_system_queries() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register(CustodyContract, (Address::generate(&env),));
+        let client = CustodyContractClient::new(&env, &contract_id);
+        
+        let guardians = create_test_guardians(&env);
+        let hot_wallet = Address::generate(&env);
+        let cold_wallet = Address::generate(&env);
+        let system_limits = create_test_system_limits();
+        
+        // Initialize
+        let vault_id = client.create_vault(
+            &guardians,
+            &hot_wallet,
+            &cold_wallet,
+            &system_limits,
+        );
+        
+        // Test query functions
+        assert_eq!(client.get_transaction_counter(&vault_id), 0);
+        assert!(!client.is_emergency_mode(&vault_id));
+        assert_eq!(client.get_hot_balance(&vault_id), 0);
+        assert_eq!(client.get_cold_balance(&vault_id), 0);
+        
+        let limits = client.get_system_limits(&vault_id);
+        assert_eq!(limits.required_approvals, 2);
+    }
+    
+    #[test]
+    fn test_denylist_blocks_payments() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        
+        let officer = Address::generate(&env);
+        let proposal_id = client.propose_compliance_officer(&vault_id, &guardians.get(0).unwrap().address, &officer);
+        assert!(client.get_compliance_officer(&vault_id).is_none());
+        assert!(client.approve_proposal(&vault_id, &guardians.get(1).unwrap().address, &proposal_id));
+        assert_eq!(client.get_compliance_officer(&vault_id), Some(officer.clone()));
+        
+        let flagged = Address::generate(&env);
+        let clean = Address::generate(&env);
+        let mut addresses = Vec::new(&env);
+        addresses.push_back(flagged.clone());
+        addresses.push_back(clean.clone());
+        client.add_to_denylist(&vault_id, &officer, &addresses);
+        assert!(client.is_denylisted(&vault_id, &flagged));
+        
+        let memo = Memo::Text(String::from_str(&env, "payout"));
+        let result = client.try_create_transaction(&vault_id, &hot_wallet, &flagged, &500, &memo, &TxType::Payment);
+        assert!(result.is_err());
+        
+        let mut removed = Vec::new(&env);
+        removed.push_back(clean.clone());
+        client.remove_from_denylist(&vault_id, &officer, &removed);
+        assert!(!client.is_denylisted(&vault_id, &clean));
+        
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &clean, &500, &memo, &TxType::Payment);
+        assert_eq!(tx_id, 1);
+        
+        // Flagged after creation: execution must be blocked as well
+        let pending_id = client.create_transaction(&vault_id, &hot_wallet, &clean, &5000, &memo, &TxType::Payment);
+        client.add_to_denylist(&vault_id, &officer, &removed);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        client.approve_transaction(&vault_id, &ceo, &pending_id, &challenge_response(&env, &client, vault_id, pending_id, &ceo));
+        let result = client.try_approve_transaction(&vault_id, &cfo, &pending_id, &challenge_response(&env, &client, vault_id, pending_id, &cfo));
+        assert!(result.is_err());
+    }
+    
+    #[test]
+    fn test_batch_transaction() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        
+        let mut legs = Vec::new(&env);
+        for _ in 0..3 {
+            legs.push_back((Address::generate(&env), 600i128, Memo::Id(42)));
+        }
+        
+        // Each leg is below the threshold, but the batch total is not
+        let tx_id = client.create_batch_transaction(&vault_id, &hot_wallet, &legs);
+        assert_eq!(env.auths()[0].0, hot_wallet);
+        let transaction = client.get_transaction(&vault_id, &tx_id).unwrap();
+        assert_eq!(transaction.amount, 1800);
+        assert_eq!(transaction.tx_type, TxType::Batch);
+        assert_eq!(transaction.status, TxStatus::AwaitingApproval);
+        
+        // Batches are listed under their leg destinations, without a single recipient
+        let (first_leg, _, _) = legs.get(0).unwrap();
+        let listed = client.list_transactions(&vault_id, &TxFilter::Destination(first_leg), &0, &10);
+        assert_eq!(listed.items.get(0).unwrap().to_address, None);
+        assert_eq!(client.list_transactions(&vault_id, &TxFilter::Destination(contract_id.clone()), &0, &10).items.len(), 0);
+        
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo));
+        assert!(client.approve_transaction(&vault_id, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cfo)));
+        
+        let legs = client.get_batch_legs(&vault_id, &tx_id).unwrap();
+        assert_eq!(legs.len(), 3);
+        assert!(legs.iter().all(|leg| leg.status == TxStatus::Executed));
+        assert_eq!(client.get_hot_balance(&vault_id), 8200);
+    }
+    
+    #[test]
+    fn test_approve_transactions_batch() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        
+        let memo = Memo::Text(String::from_str(&env, "vendor"));
+        let first = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &2000, &memo, &TxType::Payment);
+        let flagged = Address::generate(&env);
+        let second = client.create_transaction(&vault_id, &hot_wallet, &flagged, &3000, &memo, &TxType::Payment);
+        
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        client.approve_transaction(&vault_id, &ceo, &first, &challenge_response(&env, &client, vault_id, first, &ceo));
+        
+        let mut tx_ids = Vec::new(&env);
+        tx_ids.push_back(first);
+        tx_ids.push_back(second);
+        tx_ids.push_back(99);
+        let mut responses = Vec::new(&env);
+        responses.push_back(challenge_response(&env, &client, vault_id, first, &ceo));
+        responses.push_back(challenge_response(&env, &client, vault_id, second, &ceo));
+        responses.push_back(BytesN::from_array(&env, &[0u8; 64]));
+        let results = client.approve_transactions(&vault_id, &ceo, &tx_ids, &responses);
+        assert_eq!(results.get(0).unwrap().outcome, ApprovalOutcome::AlreadyApproved);
+        assert_eq!(results.get(1).unwrap().outcome, ApprovalOutcome::Recorded);
+        assert_eq!(results.get(2).unwrap().outcome, ApprovalOutcome::NotFound);
+        
+        let mut responses = Vec::new(&env);
+        responses.push_back(challenge_response(&env, &client, vault_id, first, &cfo));
+        responses.push_back(challenge_response(&env, &client, vault_id, second, &cfo));
+        responses.push_back(BytesN::from_array(&env, &[0u8; 64]));
+        
+        // A destination denylisted after creation fails its own id only
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().set(&(vault_id, DataKey::Denylisted(flagged.clone())), &true);
+        });
+        let results = client.approve_transactions(&vault_id, &cfo, &tx_ids, &responses);
+        assert_eq!(results.get(0).unwrap().outcome, ApprovalOutcome::Executed);
+        assert_eq!(results.get(1).unwrap().outcome, ApprovalOutcome::ExecutionFailed(ExecutionFailure::Denylisted));
+        assert_eq!(client.get_transaction(&vault_id, &first).unwrap().status, TxStatus::Executed);
+        let blocked = client.get_transaction(&vault_id, &second).unwrap();
+        assert_eq!(blocked.status, TxStatus::AwaitingApproval);
+        assert_eq!(blocked.approvals.len(), 1);
+        assert_eq!(client.get_guardian(&vault_id, &ceo).unwrap().approval_count, 2);
+        assert_eq!(client.get_guardian(&vault_id, &cfo).unwrap().approval_count, 1);
+    }
+    
+    #[test]
+    fn test_recurring_schedule() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        let cto = guardians.get(2).unwrap().address;
+        
+        let month = 30 * 86400;
+        let payment = ScheduledPayment {
+            from_wallet: hot_wallet.clone(),
+            to_address: Address::generate(&env),
+            amount: 2000,
+            memo: Memo::Text(String::from_str(&env, "rent")),
+        };
+        let recurrence = Recurrence {
+            interval: month,
+            start_at: 0,
+            end: ScheduleEnd::AfterCount(3),
+        };
+        assert!(client.try_create_schedule(&vault_id, &Address::generate(&env), &payment, &recurrence).is_err());
+        let schedule_id = client.create_schedule(&vault_id, &ceo, &payment, &recurrence);
+        
+        // Not executable before quorum approval, the creator's approval counts
+        assert!(client.try_execute_due(&vault_id, &schedule_id).is_err());
+        assert!(client.approve_schedule(&vault_id, &cfo, &schedule_id));
+        
+        let tx_id = client.execute_due(&vault_id, &schedule_id);
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().status, TxStatus::Executed);
+        assert!(client.try_execute_due(&vault_id, &schedule_id).is_err());
+        
+        let set_active = |guardian: &Address, is_active: bool| env.as_contract(&contract_id, || {
+            let key = (vault_id, DataKey::Guardians);
+            let mut guardians_map: Map<Address, Guardian> = env.storage().persistent().get(&key).unwrap();
+            let mut info = guardians_map.get(guardian.clone()).unwrap();
+            info.is_active = is_active;
+            guardians_map.set(guardian.clone(), info);
+            env.storage().persistent().set(&key, &guardians_map);
+        });
+        
+        // Approvals of a guardian no longer active do not carry over
+        set_active(&cfo, false);
+        env.ledger().with_mut(|ledger| ledger.timestamp += month);
+        let tx_id = client.execute_due(&vault_id, &schedule_id);
+        let transaction = client.get_transaction(&vault_id, &tx_id).unwrap();
+        assert_eq!(transaction.status, TxStatus::AwaitingApproval);
+        assert_eq!(transaction.approvals.len(), 1);
+        assert!(client.approve_transaction(&vault_id, &cto, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cto)));
+        
+        // An unpriced installment needs the maximum tier like any other transaction
+        set_active(&cfo, true);
+        let oracle_id = env.register(MockOracle, ());
+        MockOracleClient::new(&env, &oracle_id).set_price(&(2 * 10i128.pow(14)), &0);
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().set(&(vault_id, DataKey::OracleConfig), &OracleConfig {
+                oracle: oracle_id.clone(),
+                asset: Asset::Other(Symbol::new(&env, "XLM")),
+                max_age: 3600,
+            });
+        });
+        env.ledger().with_mut(|ledger| ledger.timestamp += month);
+        let tx_id = client.execute_due(&vault_id, &schedule_id);
+        let transaction = client.get_transaction(&vault_id, &tx_id).unwrap();
+        assert_eq!(transaction.value, None);
+        assert_eq!(transaction.approvals.len(), 2);
+        assert_eq!(transaction.status, TxStatus::AwaitingApproval);
+        
+        let schedule = client.get_schedule(&vault_id, &schedule_id).unwrap();
+        assert_eq!(schedule.executed_count, 3);
+        assert_eq!(schedule.status, ScheduleStatus::Completed);
+        assert_eq!(client.get_hot_balance(&vault_id), 6000);
+    }
+    
+    #[test]
+    fn test_approve_with_signatures() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        
+        let memo = Memo::Text(String::from_str(&env, "relay"));
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        let payload = client.get_approval_payload(&vault_id, &tx_id);
+        let message: std::vec::Vec<u8> = payload.iter().collect();
+        
+        let mut signatures = Vec::new(&env);
+        for (i, seed) in [[1u8; 32], [2u8; 32]].iter().enumerate() {
+            let signing_key = SigningKey::from_bytes(seed);
+            let guardian = guardians.get(i as u32).unwrap().address;
+            client.register_guardian_key(&vault_id, &guardian, &BytesN::from_array(&env, &signing_key.verifying_key().to_bytes()));
+            signatures.push_back(GuardianSignature {
+                guardian,
+                signature: BytesN::from_array(&env, &signing_key.sign(&message).to_bytes()),
+            });
+        }
+        
+        // A signature from the wrong key is rejected
+        let mut forged = Vec::new(&env);
+        forged.push_back(GuardianSignature {
+            guardian: guardians.get(0).unwrap().address,
+            signature: signatures.get(1).unwrap().signature,
+        });
+        assert!(client.try_approve_with_signatures(&vault_id, &tx_id, &forged).is_err());
+        
+        assert!(client.approve_with_signatures(&vault_id, &tx_id, &signatures));
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().status, TxStatus::Executed);
+    }
+    
+    #[test]
+    fn test_custom_account_check_auth() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        
+        let payload = BytesN::from_array(&env, &[7u8; 32]);
+        let mut signatures: Vec<GuardianSignature> = Vec::new(&env);
+        for (i, seed) in [[1u8; 32], [2u8; 32]].iter().enumerate() {
+            let signing_key = SigningKey::from_bytes(seed);
+            let guardian = guardians.get(i as u32).unwrap().address;
+            client.register_guardian_key(&vault_id, &guardian, &BytesN::from_array(&env, &signing_key.verifying_key().to_bytes()));
+            signatures.push_back(GuardianSignature {
+                guardian,
+                signature: BytesN::from_array(&env, &signing_key.sign(&payload.to_array()).to_bytes()),
+            });
+        }
+        
+        let mut single = Vec::new(&env);
+        single.push_back(signatures.get(0).unwrap());
+        let single = AccountSignature { vault_id, signatures: single };
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, single.into_val(&env), &Vec::new(&env)),
+            Err(Ok(AccountError::InsufficientSignatures)),
+        );
+        
+        let quorum = AccountSignature { vault_id, signatures };
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.clone().into_val(&env), &Vec::new(&env)),
+            Ok(()),
+        );
+        
+        let unknown = AccountSignature { vault_id: 99, signatures: Vec::new(&env) };
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, unknown.into_val(&env), &Vec::new(&env)),
+            Err(Ok(AccountError::NotInitialized)),
+        );
+        
+        // Signers are checked before any signature is verified
+        let mut forged = quorum.clone();
+        let mut entry = forged.signatures.get(0).unwrap();
+        entry.signature = BytesN::from_array(&env, &[0u8; 64]);
+        forged.signatures.set(0, entry);
+        forged.signatures.push_back(GuardianSignature { guardian: Address::generate(&env), signature: BytesN::from_array(&env, &[0u8; 64]) });
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, forged.into_val(&env), &Vec::new(&env)),
+            Err(Ok(AccountError::NotAGuardian)),
+        );
+        
+        // Transfers of the vault's asset count against the spending limits and are paid from the hot wallet
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 1000);
+        let token = Address::generate(&env);
+        let vendor = Address::generate(&env);
+        let transfer = |to: &Address, amount: i128| Vec::from_array(&env, [Context::Contract(ContractContext {
+            contract: token.clone(),
+            fn_name: Symbol::new(&env, "transfer"),
+            args: (contract_id.clone(), to.clone(), amount).into_val(&env),
+        })]);
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&vendor, 700)),
+            Ok(()),
+        );
+        assert_eq!(client.get_period_spending(&vault_id, &PeriodKey::Day(0), &None), 700);
+        assert_eq!(client.get_wallet_balance(&vault_id, &hot_wallet), Some(300));
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&vendor, 400)),
+            Err(Ok(AccountError::InsufficientBalance)),
+        );
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&vendor, 100000)),
+            Err(Ok(AccountError::LimitExceeded)),
+        );
+        
+        // With an oracle, a transfer needs the maximum tier while the price is stale
+        let oracle_id = env.register(MockOracle, ());
+        MockOracleClient::new(&env, &oracle_id).set_price(&(2 * 10i128.pow(14)), &0);
+        let oracle_key = (vault_id, DataKey::OracleConfig);
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().set(&oracle_key, &OracleConfig {
+                oracle: oracle_id.clone(),
+                asset: Asset::Stellar(token.clone()),
+                max_age: 3600,
+            });
+        });
+        env.ledger().with_mut(|ledger| ledger.timestamp = 3601);
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&vendor, 100)),
+            Err(Ok(AccountError::InsufficientSignatures)),
+        );
+        env.as_contract(&contract_id, || env.storage().persistent().remove(&oracle_key));
+        
+        // Other calls need every active guardian without a voting policy
+        let approve = Vec::from_array(&env, [Context::Contract(ContractContext {
+            contract: token.clone(),
+            fn_name: Symbol::new(&env, "approve"),
+            args: (contract_id.clone(), vendor.clone(), 700i128, 1000u32).into_val(&env),
+        })]);
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &approve),
+            Err(Ok(AccountError::InsufficientSignatures)),
+        );
+        let deploy = Vec::from_array(&env, [Context::CreateContractHostFn(CreateContractHostFnContext {
+            executable: ContractExecutable::Wasm(BytesN::from_array(&env, &[1u8; 32])),
+            salt: BytesN::from_array(&env, &[2u8; 32]),
+        })]);
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &deploy),
+            Err(Ok(AccountError::UnsupportedContext)),
+        );
+        
+        // Denylist and pause switches apply to calls the account authorizes
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().set(&(vault_id, DataKey::Denylisted(vendor.clone())), &true);
+        });
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&vendor, 100)),
+            Err(Ok(AccountError::Denylisted)),
+        );
+        client.pause_operation(&vault_id, &guardians.get(0).unwrap().address, &Operation::Payments);
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&Address::generate(&env), 100)),
+            Err(Ok(AccountError::OperationPaused)),
+        );
+        
+        // With a voting policy signers are weighed against the highest tier
+        let ceo = guardians.get(0).unwrap().address;
+        let policy = VotingPolicy {
+            weights: Vec::from_array(&env, [GuardianWeight { guardian: ceo, weight: 2 }]),
+            tiers: Vec::from_array(&env, [
+                WeightTier { min_amount: 0, required_weight: 2 },
+                WeightTier { min_amount: 10000, required_weight: 4 },
+            ]),
+        };
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().set(&(vault_id, DataKey::VotingPolicy), &policy);
+        });
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.clone().into_val(&env), &Vec::new(&env)),
+            Err(Ok(AccountError::InsufficientSignatures)),
+        );
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().set(&(vault_id, DataKey::VotingPolicy), &VotingPolicy {
+                tiers: Vec::from_array(&env, [WeightTier { min_amount: 0, required_weight: 3 }]),
+                ..policy
+            });
+        });
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &Vec::new(&env)),
+            Ok(()),
+        );
+    }
+    
+    #[test]
+    fn test_custom_account_vault_isolation() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_a, _, hot_wallet_a, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_a, &hot_wallet_a, WalletType::Hot, 5000);
+        
+        let guardians_b = create_test_guardians(&env);
+        let hot_wallet_b = Address::generate(&env);
+        let vault_b = client.create_vault(&guardians_b, &hot_wallet_b, &Address::generate(&env), &create_test_system_limits());
+        fund_wallet(&env, &contract_id, vault_b, &hot_wallet_b, WalletType::Hot, 100);
+        
+        // Vault B's quorum signs for the shared contract address
+        let payload = BytesN::from_array(&env, &[9u8; 32]);
+        let mut signatures: Vec<GuardianSignature> = Vec::new(&env);
+        for guardian in guardians_b.iter().take(2) {
+            client.register_guardian_key(&vault_b, &guardian.address, &public_key(&env, &guardian.address));
+            signatures.push_back(GuardianSignature {
+                guardian: guardian.address.clone(),
+                signature: BytesN::from_array(&env, &signing_key(&env, &guardian.address).sign(&payload.to_array()).to_bytes()),
+            });
+        }
+        let quorum = AccountSignature { vault_id: vault_b, signatures };
+        
+        // It cannot authorize calls into vault A
+        let shutdown = Vec::from_array(&env, [Context::Contract(ContractContext {
+            contract: contract_id.clone(),
+            fn_name: Symbol::new(&env, "emergency_shutdown"),
+            args: (vault_a, guardians_b.get(0).unwrap().address).into_val(&env),
+        })]);
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &shutdown),
+            Err(Ok(AccountError::VaultMismatch)),
+        );
+        
+        // Without an asset of its own, vault B cannot move tokens held for vault A
+        let token = Address::generate(&env);
+        let vendor = Address::generate(&env);
+        let transfer = |token: &Address, amount: i128| Vec::from_array(&env, [Context::Contract(ContractContext {
+            contract: token.clone(),
+            fn_name: Symbol::new(&env, "transfer"),
+            args: (contract_id.clone(), vendor.clone(), amount).into_val(&env),
+        })]);
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&token, 50)),
+            Err(Ok(AccountError::UnsupportedContext)),
+        );
+        
+        // With its asset configured, only its own hot wallet balance can be moved
+        let oracle_id = env.register(MockOracle, ());
+        MockOracleClient::new(&env, &oracle_id).set_price(&(2 * 10i128.pow(14)), &0);
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().set(&(vault_b, DataKey::OracleConfig), &OracleConfig {
+                oracle: oracle_id.clone(),
+                asset: Asset::Stellar(token.clone()),
+                max_age: 3600,
+            });
+        });
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&token, 1000)),
+            Err(Ok(AccountError::InsufficientBalance)),
+        );
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&Address::generate(&env), 50)),
+            Err(Ok(AccountError::UnsupportedContext)),
+        );
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &transfer(&token, 100)),
+            Ok(()),
+        );
+        assert_eq!(client.get_wallet_balance(&vault_b, &hot_wallet_b), Some(0));
+        assert_eq!(client.get_wallet_balance(&vault_a, &hot_wallet_a), Some(5000));
+        
+        // Calls that cannot be tied to a vault's balance are refused
+        let approve = Vec::from_array(&env, [Context::Contract(ContractContext {
+            contract: token.clone(),
+            fn_name: Symbol::new(&env, "approve"),
+            args: (contract_id.clone(), vendor.clone(), 700i128, 1000u32).into_val(&env),
+        })]);
+        assert_eq!(
+            env.try_invoke_contract_check_auth::<AccountError>(&contract_id, &payload, quorum.into_val(&env), &approve),
+            Err(Ok(AccountError::UnsupportedContext)),
+        );
+    }
+    
+    #[test]
+    fn test_challenge_response_required() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        
+        let memo = Memo::Text(String::from_str(&env, "ocra"));
+        let small = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &500, &memo, &TxType::Payment);
+        assert!(client.get_challenge(&vault_id, &small).is_none());
+        
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        assert!(client.get_challenge(&vault_id, &tx_id).is_some());
+        
+        // Without a registered key there is no valid response
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        let unregistered = BytesN::from_array(&env, &signing_key(&env, &ceo).sign(&[0u8; 32]).to_bytes());
+        assert!(client.try_approve_transaction(&vault_id, &ceo, &tx_id, &unregistered).is_err());
+        
+        // A response signed with another guardian's key does not verify
+        let wrong = challenge_response(&env, &client, vault_id, tx_id, &cfo);
+        assert!(client.try_approve_transaction(&vault_id, &ceo, &tx_id, &wrong).is_err());
+        
+        client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo));
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().approvals.len(), 1);
+    }
+    
+    #[test]
+    fn test_ephemeral_account_lifecycle() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        
+        let memo = Memo::Text(String::from_str(&env, "private"));
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        let ephemeral_address = Address::generate(&env);
+        client.register_ephemeral_account(&vault_id, &tx_id, &ephemeral_address, &7, &100, &3600);
+        
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo));
+        client.approve_transaction(&vault_id, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cfo));
+        
+        let ephemeral = client.get_ephemeral_account(&vault_id, &tx_id).unwrap();
+        assert_eq!(ephemeral.status, EphemeralStatus::Funded);
+        assert_eq!(ephemeral.funded_amount, 5100);
+        assert_eq!(client.get_hot_balance(&vault_id), 4900);
+        
+        // Sweeps credit the hot wallet and stop while deposits are paused
+        client.pause_operation(&vault_id, &ceo, &Operation::Deposits);
+        assert!(client.try_sweep_ephemeral_account(&vault_id, &tx_id, &40).is_err());
+        let proposal_id = client.propose_unpause(&vault_id, &ceo, &Operation::Deposits);
+        client.approve_proposal(&vault_id, &cfo, &proposal_id);
+        client.sweep_ephemeral_account(&vault_id, &tx_id, &40);
+        assert_eq!(client.get_ephemeral_account(&vault_id, &tx_id).unwrap().status, EphemeralStatus::Swept);
+        assert_eq!(client.get_hot_balance(&vault_id), 4940);
+        
+        // Unused entries can be invalidated once expired
+        let unused_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &2000, &memo, &TxType::Payment);
+        
+        // Derived keys are never reused, even after their account was swept
+        assert!(client.try_register_ephemeral_account(&vault_id, &unused_id, &ephemeral_address, &8, &100, &3600).is_err());
+        assert!(client.try_register_ephemeral_account(&vault_id, &unused_id, &Address::generate(&env), &7, &100, &3600).is_err());
+        client.register_ephemeral_account(&vault_id, &unused_id, &Address::generate(&env), &8, &100, &3600);
+        assert!(client.try_expire_ephemeral_account(&vault_id, &unused_id).is_err());
+        env.ledger().with_mut(|ledger| ledger.timestamp = 3601);
+        client.expire_ephemeral_account(&vault_id, &unused_id);
+        assert_eq!(client.get_ephemeral_account(&vault_id, &unused_id).unwrap().status, EphemeralStatus::Expired);
+    }
+    
+    #[test]
+    fn test_list_transactions() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 100000);
+        
+        let memo = Memo::Text(String::from_str(&env, "list"));
+        let vendor = Address::generate(&env);
+        for _ in 0..3 {
+            client.create_transaction(&vault_id, &hot_wallet, &vendor, &500, &memo, &TxType::Payment);
+        }
+        let pending_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        let ceo = guardians.get(0).unwrap().address;
+        client.approve_transaction(&vault_id, &ceo, &pending_id, &challenge_response(&env, &client, vault_id, pending_id, &ceo));
+        
+        let page = client.list_transactions(&vault_id, &TxFilter::All, &0, &3);
+        assert_eq!(page.items.len(), 3);
+        assert_eq!(page.next_cursor, Some(3));
+        let page = client.list_transactions(&vault_id, &TxFilter::All, &3, &3);
+        assert_eq!(page.items.get(0).unwrap().id, pending_id);
+        assert_eq!(page.next_cursor, None);
+        
+        assert_eq!(client.list_transactions(&vault_id, &TxFilter::Destination(vendor), &0, &10).items.len(), 3);
+        assert_eq!(client.list_transactions(&vault_id, &TxFilter::Status(TxStatus::Executed), &0, &10).items.len(), 3);
+        assert_eq!(client.list_transactions(&vault_id, &TxFilter::Wallet(hot_wallet), &0, &10).items.len(), 4);
+        
+        let awaiting = client.list_transactions(&vault_id, &TxFilter::Guardian(ceo), &0, &10);
+        assert_eq!(awaiting.items.len(), 1);
+        assert_eq!(awaiting.items.get(0).unwrap().approval_count, 1);
+        
+        // Status indexes follow transitions
+        let cfo = guardians.get(1).unwrap().address;
+        client.approve_transaction(&vault_id, &cfo, &pending_id, &challenge_response(&env, &client, vault_id, pending_id, &cfo));
+        assert_eq!(client.list_transactions(&vault_id, &TxFilter::Status(TxStatus::AwaitingApproval), &0, &10).items.len(), 0);
+        assert_eq!(client.list_transactions(&vault_id, &TxFilter::Status(TxStatus::Executed), &0, &10).items.len(), 4);
+    }
+    
+    #[test]
+    fn test_list_transactions_across_index_pages() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 100000);
+        
+        let memo = Memo::Text(String::from_str(&env, "pages"));
+        let vendor = Address::generate(&env);
+        for _ in 0..60 {
+            client.create_transaction(&vault_id, &hot_wallet, &vendor, &1500, &memo, &TxType::Payment);
+        }
+        
+        let awaiting = TxFilter::Status(TxStatus::AwaitingApproval);
+        let first = client.list_transactions(&vault_id, &awaiting, &40, &20);
+        assert_eq!(first.items.len(), 20);
+        assert_eq!(first.next_cursor, None);
+        assert_eq!(first.items.get(0).unwrap().id, 41);
+        assert_eq!(first.items.get(19).unwrap().id, 60);
+        
+        // Executing transactions swaps the last entry into the freed slot
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        for tx_id in [3u64, 60, 55] {
+            client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo));
+            client.approve_transaction(&vault_id, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cfo));
+        }
+        
+        let first = client.list_transactions(&vault_id, &awaiting, &0, &50);
+        assert_eq!(first.next_cursor, Some(50));
+        let second = client.list_transactions(&vault_id, &awaiting, &50, &50);
+        assert_eq!(second.items.len(), 7);
+        assert_eq!(second.next_cursor, None);
+        
+        let mut listed: Vec<u64> = Vec::new(&env);
+        for item in first.items.iter().chain(second.items.iter()) {
+            assert!(matches!(item.status, TxStatus::AwaitingApproval));
+            assert!(!listed.contains(item.id));
+            listed.push_back(item.id);
+        }
+        assert_eq!(listed.len(), 57);
+        assert_eq!(client.list_transactions(&vault_id, &TxFilter::Status(TxStatus::Executed), &0, &10).items.len(), 3);
+        assert_eq!(client.list_transactions(&vault_id, &TxFilter::Destination(vendor), &50, &50).items.len(), 10);
+    }
+    
+    #[test]
+    fn test_guardian_and_config_queries() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, cold_wallet) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        
+        let listed = client.list_guardians(&vault_id);
+        assert_eq!(listed.len(), 3);
+        assert!(listed.iter().all(|g| guardians.iter().any(|expected| expected.address == g.address)));
+        
+        let memo = Memo::Text(String::from_str(&env, "reserve"));
+        client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &4000, &memo, &TxType::Payment);
+        let wallet = client.get_wallet_info(&vault_id, &hot_wallet).unwrap();
+        assert_eq!(wallet.info.reserved_balance, 4000);
+        assert_eq!(wallet.available_balance, 6000);
+        
+        let config = client.get_config(&vault_id);
+        assert_eq!(config.guardian_count, 3);
+        assert_eq!(config.active_guardian_count, 3);
+        assert_eq!(config.cold_wallet.info.address, cold_wallet);
+        assert_eq!(config.transaction_counter, 1);
+        assert!(!config.emergency_mode);
     }
     
-    /// Get cold wallet balance
-    pub fn get_cold_balance(env: Env) -> i128 {
-        let cold_wallet: Address = env.storage().instance().get(&DataKey::ColdWallet).unwrap();
-        Self::get_wallet_balance(env, cold_wallet).unwrap_or(0)
+    #[test]
+    fn test_spending_status() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, _, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        
+        let memo = Memo::Text(String::from_str(&env, "usage"));
+        client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &700, &memo, &TxType::Payment);
+        
+        let status = client.get_spending_status(&vault_id);
+        assert_eq!(status.daily.spent, 700);
+        assert_eq!(status.daily.remaining, 100000 - 700);
+        assert_eq!(status.monthly.remaining, 1000000 - 700);
+        let hot = status.wallets.get(0).unwrap();
+        assert_eq!(hot.wallet, hot_wallet);
+        assert_eq!(hot.daily_spent, 700);
+        assert_eq!(status.wallets.get(1).unwrap().daily_spent, 0);
+        
+        // Past periods stay queryable
+        env.ledger().with_mut(|ledger| ledger.timestamp += 86400);
+        assert_eq!(client.get_spending_status(&vault_id).daily.spent, 0);
+        assert_eq!(client.get_period_spending(&vault_id, &PeriodKey::Day(0), &None), 700);
+        assert_eq!(client.get_period_spending(&vault_id, &PeriodKey::Day(0), &Some(hot_wallet)), 700);
     }
     
-    /// Emergency shutdown
-    pub fn emergency_shutdown(env: Env, guardian: Address) {
-        guardian.require_auth();
+    #[test]
+    fn test_limits_update_governance() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, _, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        let cto = guardians.get(2).unwrap().address;
         
-        Self::check_initialized(&env);
+        // Invalid limits are rejected up front
+        let mut invalid = create_test_system_limits();
+        invalid.daily_limit = invalid.monthly_limit + 1;
+        assert!(client.try_propose_limits_update(&vault_id, &ceo, &invalid).is_err());
         
-        // Verify guardian
-        let guardians: Map<Address, Guardian> = env.storage().instance()
-            .get(&DataKey::Guardians)
-            .unwrap_or_else(|| panic!("Contract not initialized"));
+        // Tightening takes the normal quorum
+        let mut tighter = create_test_system_limits();
+        tighter.daily_limit = 50000;
+        let proposal_id = client.propose_limits_update(&vault_id, &ceo, &tighter);
+        assert_eq!(client.get_proposal(&vault_id, &proposal_id).unwrap().required_approvals, 2);
+        assert!(client.approve_proposal(&vault_id, &cfo, &proposal_id));
+        assert_eq!(client.get_system_limits(&vault_id).daily_limit, 50000);
         
-        let guardian_info = guardians.get(guardian.clone()).unwrap_or_else(|| panic!("Not a guardian"));
-        if !guardian_info.is_active {
-            panic!("Guardian not active");
-        }
+        // Loosening needs every active guardian
+        let looser = create_test_system_limits();
+        let proposal_id = client.propose_limits_update(&vault_id, &ceo, &looser);
+        assert!(!client.approve_proposal(&vault_id, &cfo, &proposal_id));
+        assert_eq!(client.get_system_limits(&vault_id).daily_limit, 50000);
+        assert!(client.approve_proposal(&vault_id, &cto, &proposal_id));
+        assert_eq!(client.get_system_limits(&vault_id).daily_limit, 100000);
         
-        // Activate emergency mode
-        env.storage().instance().set(&DataKey::EmergencyMode, &true);
-        env.storage().instance().set(&DataKey::EmergencyInitiator, &guardian);
+        let history = client.get_limits_history(&vault_id);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(1).unwrap().limits.daily_limit, 50000);
         
-        log!(&env, "Emergency shutdown activated by guardian");
+        // Classification follows the limits in force when approvals come in
+        let mut moderate = create_test_system_limits();
+        moderate.daily_limit = 80000;
+        let moderate_id = client.propose_limits_update(&vault_id, &ceo, &moderate);
+        assert_eq!(client.get_proposal(&vault_id, &moderate_id).unwrap().required_approvals, 2);
+        let tighter_id = client.propose_limits_update(&vault_id, &ceo, &tighter);
+        assert!(client.approve_proposal(&vault_id, &cfo, &tighter_id));
+        assert!(!client.approve_proposal(&vault_id, &cfo, &moderate_id));
+        assert_eq!(client.get_proposal(&vault_id, &moderate_id).unwrap().required_approvals, 3);
+        assert_eq!(client.get_system_limits(&vault_id).daily_limit, 50000);
+        
+        // Stale proposals expire
+        let proposal_id = client.propose_limits_update(&vault_id, &ceo, &looser);
+        env.ledger().with_mut(|ledger| ledger.timestamp += PROPOSAL_LIFETIME + 1);
+        assert!(client.try_approve_proposal(&vault_id, &cfo, &proposal_id).is_err());
+        assert!(client.try_approve_proposal(&vault_id, &cto, &moderate_id).is_err());
     }
     
-    /// Get system info
-    pub fn get_system_limits(env: Env) -> SystemLimits {
-        env.storage().instance().get(&DataKey::SystemLimits).unwrap()
+    #[test]
+    fn test_pause_operation() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, cold_wallet) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        fund_wallet(&env, &contract_id, vault_id, &cold_wallet, WalletType::Cold, 10000);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        
+        client.pause_operation(&vault_id, &ceo, &Operation::Payments);
+        assert!(client.is_operation_paused(&vault_id, &Operation::Payments));
+        
+        // Payments are blocked, other operations keep working
+        let memo = Memo::Text(String::from_str(&env, "paused"));
+        let to_address = Address::generate(&env);
+        assert!(client.try_create_transaction(&vault_id, &hot_wallet, &to_address, &500, &memo, &TxType::Payment).is_err());
+        client.create_transaction(&vault_id, &hot_wallet, &to_address, &500, &memo, &TxType::Withdrawal);
+        
+        client.pause_operation(&vault_id, &ceo, &Operation::ColdWallet);
+        assert!(client.try_create_transaction(&vault_id, &cold_wallet, &to_address, &500, &memo, &TxType::Withdrawal).is_err());
+        
+        // Unpausing takes quorum
+        let proposal_id = client.propose_unpause(&vault_id, &ceo, &Operation::Payments);
+        assert!(client.is_operation_paused(&vault_id, &Operation::Payments));
+        client.approve_proposal(&vault_id, &cfo, &proposal_id);
+        assert!(!client.is_operation_paused(&vault_id, &Operation::Payments));
+        client.create_transaction(&vault_id, &hot_wallet, &to_address, &500, &memo, &TxType::Payment);
     }
     
-    /// Check if emergency mode is active
-    pub fn is_emergency_mode(env: Env) -> bool {
-        env.storage().instance().get(&DataKey::EmergencyMode).unwrap_or(false)
+    #[test]
+    fn test_named_wallets() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, _, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        let cto = guardians.get(2).unwrap().address;
+        
+        let payroll = Address::generate(&env);
+        let wallet = NamedWallet {
+            name: Symbol::new(&env, "payroll"),
+            address: payroll.clone(),
+            wallet_type: WalletType::Hot,
+            policy: WalletPolicy {
+                daily_limit: 3000,
+                monthly_limit: 10000,
+                high_value_threshold: 100,
+                required_approvals: 3,
+            },
+        };
+        let proposal_id = client.propose_wallet_creation(&vault_id, &ceo, &wallet);
+        assert!(client.get_named_wallet(&vault_id, &wallet.name).is_none());
+        client.approve_proposal(&vault_id, &cfo, &proposal_id);
+        assert_eq!(client.get_named_wallet(&vault_id, &wallet.name).unwrap().address, payroll);
+        assert_eq!(client.list_named_wallets(&vault_id).len(), 1);
+        
+        fund_wallet(&env, &contract_id, vault_id, &payroll, WalletType::Hot, 5000);
+        
+        // The wallet's own threshold, limits and approval policy apply
+        let memo = Memo::Text(String::from_str(&env, "salary"));
+        let to_address = Address::generate(&env);
+        assert!(client.try_create_transaction(&vault_id, &payroll, &to_address, &3500, &memo, &TxType::Payment).is_err());
+        let tx_id = client.create_transaction(&vault_id, &payroll, &to_address, &2000, &memo, &TxType::Payment);
+        client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo));
+        assert!(!client.approve_transaction(&vault_id, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cfo)));
+        assert!(client.approve_transaction(&vault_id, &cto, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cto)));
+        
+        // Only empty wallets can be retired
+        let proposal_id = client.propose_wallet_retirement(&vault_id, &ceo, &wallet.name);
+        assert!(client.try_approve_proposal(&vault_id, &cfo, &proposal_id).is_err());
+        let tx_id = client.create_transaction(&vault_id, &payroll, &to_address, &50, &memo, &TxType::Payment);
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().status, TxStatus::Executed);
+        env.ledger().with_mut(|ledger| ledger.timestamp += 86400);
+        let drain_id = client.create_transaction(&vault_id, &payroll, &to_address, &2950, &memo, &TxType::Payment);
+        for guardian in [&ceo, &cfo, &cto] {
+            client.approve_transaction(&vault_id, guardian, &drain_id, &challenge_response(&env, &client, vault_id, drain_id, guardian));
+        }
+        client.approve_proposal(&vault_id, &cfo, &proposal_id);
+        assert!(!client.get_wallet_info(&vault_id, &payroll).unwrap().info.is_active);
+        assert!(client.try_create_transaction(&vault_id, &payroll, &to_address, &1, &memo, &TxType::Payment).is_err());
     }
     
-    /// Get transaction counter
-    pub fn get_transaction_counter(env: Env) -> u64 {
-        env.storage().instance().get(&DataKey::TransactionCounter).unwrap_or(0)
+    #[test]
+    fn test_vault_isolation() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_a, guardians_a, hot_a, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        
+        let guardians_b = create_test_guardians(&env);
+        let hot_b = Address::generate(&env);
+        let vault_b = client.create_vault(&guardians_b, &hot_b, &Address::generate(&env), &create_test_system_limits());
+        assert_ne!(vault_a, vault_b);
+        assert_eq!(client.get_vault_count(), 2);
+        fund_wallet(&env, &contract_id, vault_a, &hot_a, WalletType::Hot, 10000);
+        fund_wallet(&env, &contract_id, vault_b, &hot_b, WalletType::Hot, 10000);
+        
+        // Each vault has its own guardian set, wallets and transaction counter
+        let intruder = guardians_a.get(0).unwrap().address;
+        assert!(client.get_guardian(&vault_b, &intruder).is_none());
+        assert!(client.get_wallet_info(&vault_a, &hot_b).is_none());
+        let memo = Memo::Text(String::from_str(&env, "payout"));
+        let tx_a = client.create_transaction(&vault_a, &hot_a, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        let tx_b = client.create_transaction(&vault_b, &hot_b, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        assert_eq!(tx_a, tx_b);
+        assert!(client.try_create_transaction(&vault_a, &hot_b, &Address::generate(&env), &500, &memo, &TxType::Payment).is_err());
+        
+        // Vault A's guardians cannot approve, pause, govern or shut down vault B
+        let response = challenge_response(&env, &client, vault_b, tx_b, &intruder);
+        assert!(client.try_approve_transaction(&vault_b, &intruder, &tx_b, &response).is_err());
+        assert!(client.try_pause_operation(&vault_b, &intruder, &Operation::Payments).is_err());
+        assert!(client.try_propose_limits_update(&vault_b, &intruder, &create_test_system_limits()).is_err());
+        assert!(client.try_propose_compliance_officer(&vault_b, &intruder, &intruder).is_err());
+        assert!(client.try_emergency_shutdown(&vault_b, &intruder).is_err());
+        
+        // Approving the same tx id in vault A leaves vault B untouched
+        let other = guardians_a.get(1).unwrap().address;
+        client.approve_transaction(&vault_a, &intruder, &tx_a, &challenge_response(&env, &client, vault_a, tx_a, &intruder));
+        assert!(client.approve_transaction(&vault_a, &other, &tx_a, &challenge_response(&env, &client, vault_a, tx_a, &other)));
+        assert_eq!(client.get_transaction(&vault_a, &tx_a).unwrap().status, TxStatus::Executed);
+        let pending = client.get_transaction(&vault_b, &tx_b).unwrap();
+        assert_eq!(pending.status, TxStatus::AwaitingApproval);
+        assert_eq!(pending.approvals.len(), 0);
+        assert_eq!(client.get_wallet_balance(&vault_b, &hot_b), Some(10000));
+        
+        // Emergency mode is scoped to a single vault
+        client.emergency_shutdown(&vault_a, &intruder);
+        assert!(client.is_emergency_mode(&vault_a));
+        assert!(!client.is_emergency_mode(&vault_b));
     }
     
-    // ==================== INTERNAL FUNCTIONS ====================
-    
-    fn execute_transaction_internal(env: &Env, tx_id: u64) {
-        let tx_key = DataKey::Transaction(tx_id);
-        let mut transaction: Transaction = env.storage().instance()
-            .get(&tx_key)
-            .unwrap_or_else(|| panic!("Transaction not found"));
+    #[test]
+    fn test_guardian_recovery() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
         
-        // Update wallet balances
-        let from_wallet_key = DataKey::WalletInfo(transaction.from_wallet.clone());
-        let mut from_wallet: WalletInfo = env.storage().instance()
-            .get(&from_wallet_key)
-            .unwrap_or_else(|| panic!("Wallet not found"));
+        // Recovery set is installed by guardian quorum, with a long minimum timelock
+        let lawyer = Address::generate(&env);
+        let board = Address::generate(&env);
+        let mut config = RecoveryConfig {
+            guardians: Vec::from_array(&env, [lawyer.clone(), board.clone(), Address::generate(&env)]),
+            threshold: 2,
+            timelock: 86400,
+        };
+        assert!(client.try_propose_recovery_config(&vault_id, &ceo, &config).is_err());
+        config.timelock = MIN_RECOVERY_TIMELOCK;
+        let proposal_id = client.propose_recovery_config(&vault_id, &ceo, &config);
+        client.approve_proposal(&vault_id, &cfo, &proposal_id);
+        assert_eq!(client.get_recovery_config(&vault_id), Some(config));
         
-        // Execute the transfer
-        from_wallet.balance -= transaction.amount;
-        from_wallet.reserved_balance -= transaction.amount;
+        // Any active guardian can cancel during the window
+        let new_guardians = create_test_guardians(&env);
+        assert!(client.try_initiate_recovery(&vault_id, &ceo, &new_guardians).is_err());
+        let recovery_id = client.initiate_recovery(&vault_id, &lawyer, &new_guardians);
+        let events = env.events().all();
+        assert_eq!(events.len(), 1);
+        let expected_topics: Vec<soroban_sdk::Val> = (Symbol::new(&env, "recovery_initiated"), vault_id).into_val(&env);
+        assert_eq!(events.get(0).unwrap().1, expected_topics);
+        assert!(client.try_initiate_recovery(&vault_id, &board, &new_guardians).is_err());
+        assert!(client.try_execute_recovery(&vault_id, &recovery_id).is_err());
+        client.approve_recovery(&vault_id, &board, &recovery_id);
+        assert!(client.get_recovery(&vault_id, &recovery_id).unwrap().executable_at.is_some());
+        assert!(client.try_execute_recovery(&vault_id, &recovery_id).is_err());
+        client.cancel_recovery(&vault_id, &cfo, &recovery_id);
+        let cancelled = client.get_recovery(&vault_id, &recovery_id).unwrap();
+        assert_eq!(cancelled.status, RecoveryStatus::Cancelled);
+        assert_eq!(cancelled.cancelled_by, Some(cfo.clone()));
         
-        // Update transaction status
-        transaction.status = TxStatus::Executed;
-        transaction.executed_at = Some(env.ledger().timestamp());
+        // Duplicate guardians are rejected
+        let mut duplicated = new_guardians.clone();
+        duplicated.set(2, new_guardians.get(0).unwrap());
+        assert!(client.try_initiate_recovery(&vault_id, &lawyer, &duplicated).is_err());
         
-        // Update spending tracking
-        Self::update_spending_tracking(env, transaction.amount);
+        // Approvals given by the old guardians are left pending
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        let stale_tx = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &2000, &Memo::None, &TxType::Payment);
+        client.approve_transaction(&vault_id, &ceo, &stale_tx, &challenge_response(&env, &client, vault_id, stale_tx, &ceo));
+        let stale_proposal = client.propose_compliance_officer(&vault_id, &ceo, &Address::generate(&env));
         
-        // Store updates
-        env.storage().instance().set(&from_wallet_key, &from_wallet);
-        env.storage().instance().set(&tx_key, &transaction);
+        // Unopposed, the recovery replaces the guardian set after the timelock
+        let recovery_id = client.initiate_recovery(&vault_id, &lawyer, &new_guardians);
+        client.approve_recovery(&vault_id, &board, &recovery_id);
+        env.ledger().with_mut(|ledger| ledger.timestamp += MIN_RECOVERY_TIMELOCK);
+        client.execute_recovery(&vault_id, &recovery_id);
+        assert_eq!(client.get_recovery(&vault_id, &recovery_id).unwrap().status, RecoveryStatus::Executed);
+        assert!(client.get_guardian(&vault_id, &ceo).is_none());
+        assert!(client.try_cancel_recovery(&vault_id, &ceo, &recovery_id).is_err());
         
-        log!(env, "Transaction {} executed successfully", tx_id);
-    }
-    
-    fn check_initialized(env: &Env) {
-        if !env.storage().instance().has(&DataKey::Initialized) {
-            panic!("Contract not initialized");
-        }
-    }
-    
-    fn check_emergency_mode(env: &Env) {
-        let emergency_mode: bool = env.storage().instance()
-            .get(&DataKey::EmergencyMode)
-            .unwrap_or(false);
-            
-        if emergency_mode {
-            panic!("Emergency mode active");
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &Memo::None, &TxType::Payment);
+        for guardian in [new_guardians.get(0).unwrap().address, new_guardians.get(1).unwrap().address] {
+            client.approve_transaction(&vault_id, &guardian, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &guardian));
         }
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().status, TxStatus::Executed);
+        
+        // Replaced guardians' approvals no longer count
+        let new_ceo = new_guardians.get(0).unwrap().address;
+        assert!(!client.approve_transaction(&vault_id, &new_ceo, &stale_tx, &challenge_response(&env, &client, vault_id, stale_tx, &new_ceo)));
+        let transaction = client.get_transaction(&vault_id, &stale_tx).unwrap();
+        assert_eq!(transaction.approvals.len(), 1);
+        assert_eq!(transaction.approval_weight, 1);
+        assert!(!client.approve_proposal(&vault_id, &new_ceo, &stale_proposal));
+        assert_eq!(client.get_proposal(&vault_id, &stale_proposal).unwrap().approvals, Vec::from_array(&env, [new_ceo]));
     }
     
-    fn check_spending_limits(env: &Env, amount: i128) {
-        let system_limits: SystemLimits = env.storage().instance().get(&DataKey::SystemLimits).unwrap();
+    #[test]
+    fn test_dead_man_switch() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, cold_wallet) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        fund_wallet(&env, &contract_id, vault_id, &cold_wallet, WalletType::Cold, 50000);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
         
-        // Check daily limit
-        let today = env.ledger().timestamp() / 86400; // Convert to days
-        let daily_spent: i128 = env.storage().instance()
-            .get(&DataKey::DailySpent(today))
-            .unwrap_or(0);
-            
-        if daily_spent + amount > system_limits.daily_limit {
-            panic!("Exceeds daily limit");
-        }
+        let heir = Address::generate(&env);
+        let config = InheritanceConfig {
+            beneficiary: heir.clone(),
+            inactivity_period: MIN_INACTIVITY_PERIOD,
+            timelock: MIN_INHERITANCE_TIMELOCK,
+        };
+        let proposal_id = client.propose_inheritance_config(&vault_id, &ceo, &config);
+        client.approve_proposal(&vault_id, &cfo, &proposal_id);
+        assert_eq!(client.get_inheritance_config(&vault_id), Some(config));
         
-        // Check monthly limit
-        let current_month = today / 30; // Approximate month
-        let monthly_spent: i128 = env.storage().instance()
-            .get(&DataKey::MonthlySpent(current_month))
-            .unwrap_or(0);
-            
-        if monthly_spent + amount > system_limits.monthly_limit {
-            panic!("Exceeds monthly limit");
-        }
+        // Nothing can be claimed while guardians are active
+        assert!(client.try_initiate_inheritance(&vault_id, &heir).is_err());
+        env.ledger().with_mut(|ledger| ledger.timestamp += MIN_INACTIVITY_PERIOD);
+        assert!(client.try_initiate_inheritance(&vault_id, &ceo).is_err());
+        client.initiate_inheritance(&vault_id, &heir);
+        assert_eq!(client.get_wallet_info(&vault_id, &cold_wallet).unwrap().available_balance, 0);
+        
+        // A heartbeat cancels the claim and resets the timer
+        client.heartbeat(&vault_id, &cfo);
+        assert_eq!(client.get_inheritance_claim(&vault_id).unwrap().status, InheritanceStatus::Cancelled);
+        assert_eq!(client.get_wallet_info(&vault_id, &cold_wallet).unwrap().available_balance, 50000);
+        assert!(client.try_initiate_inheritance(&vault_id, &heir).is_err());
+        
+        // Any guardian action counts as activity, not only heartbeats
+        env.ledger().with_mut(|ledger| ledger.timestamp += MIN_INACTIVITY_PERIOD - 1);
+        client.register_guardian_key(&vault_id, &ceo, &public_key(&env, &ceo));
+        env.ledger().with_mut(|ledger| ledger.timestamp += 1);
+        assert_eq!(client.get_last_guardian_activity(&vault_id), env.ledger().timestamp() - 1);
+        assert!(client.try_initiate_inheritance(&vault_id, &heir).is_err());
+        
+        // Emergency mode and the cold wallet pause hold claims back
+        let set_flag = |key: DataKey, on: bool| env.as_contract(&contract_id, || {
+            if on {
+                env.storage().persistent().set(&(vault_id, key), &true);
+            } else {
+                env.storage().persistent().remove(&(vault_id, key));
+            }
+        });
+        env.ledger().with_mut(|ledger| ledger.timestamp += MIN_INACTIVITY_PERIOD);
+        set_flag(DataKey::EmergencyMode, true);
+        assert!(client.try_initiate_inheritance(&vault_id, &heir).is_err());
+        set_flag(DataKey::EmergencyMode, false);
+        set_flag(DataKey::Paused(Operation::ColdWallet), true);
+        assert!(client.try_initiate_inheritance(&vault_id, &heir).is_err());
+        set_flag(DataKey::Paused(Operation::ColdWallet), false);
+        
+        // An approval during the timelock cancels the claim like a heartbeat
+        client.initiate_inheritance(&vault_id, &heir);
+        let memo = Memo::Text(String::from_str(&env, "payroll"));
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        env.ledger().with_mut(|ledger| ledger.timestamp += MIN_INHERITANCE_TIMELOCK - 1);
+        client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo));
+        env.ledger().with_mut(|ledger| ledger.timestamp += 1);
+        assert!(client.try_execute_inheritance(&vault_id).is_err());
+        assert_eq!(client.get_inheritance_claim(&vault_id).unwrap().status, InheritanceStatus::Cancelled);
+        assert_eq!(client.get_wallet_info(&vault_id, &cold_wallet).unwrap().available_balance, 50000);
+        
+        // Activity that leaves the claim pending, like a custom account signature, still blocks the payout
+        env.ledger().with_mut(|ledger| ledger.timestamp += MIN_INACTIVITY_PERIOD);
+        client.initiate_inheritance(&vault_id, &heir);
+        env.ledger().with_mut(|ledger| ledger.timestamp += MIN_INHERITANCE_TIMELOCK);
+        let signed_at = env.ledger().timestamp() - 1;
+        env.as_contract(&contract_id, || env.storage().persistent().set(&(vault_id, DataKey::LastHeartbeat), &signed_at));
+        assert!(client.try_execute_inheritance(&vault_id).is_err());
+        client.heartbeat(&vault_id, &cfo);
+        
+        // Without further activity the beneficiary receives the cold funds after the timelock
+        env.ledger().with_mut(|ledger| ledger.timestamp += MIN_INACTIVITY_PERIOD);
+        client.initiate_inheritance(&vault_id, &heir);
+        assert!(client.try_execute_inheritance(&vault_id).is_err());
+        env.ledger().with_mut(|ledger| ledger.timestamp += MIN_INHERITANCE_TIMELOCK);
+        set_flag(DataKey::Paused(Operation::ColdWallet), true);
+        assert!(client.try_execute_inheritance(&vault_id).is_err());
+        set_flag(DataKey::Paused(Operation::ColdWallet), false);
+        client.execute_inheritance(&vault_id);
+        let claim = client.get_inheritance_claim(&vault_id).unwrap();
+        assert_eq!(claim.status, InheritanceStatus::Executed);
+        assert_eq!(claim.amount, 50000);
+        assert_eq!(client.get_cold_balance(&vault_id), 0);
     }
     
-    fn update_spending_tracking(env: &Env, amount: i128) {
-        let today = env.ledger().timestamp() / 86400;
-        let current_month = today / 30;
+    #[test]
+    fn test_guardian_delegation() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 50000);
         
-        // Update daily spending
-        let daily_spent: i128 = env.storage().instance()
-            .get(&DataKey::DailySpent(today))
-            .unwrap_or(0);
-        env.storage().instance().set(&DataKey::DailySpent(today), &(daily_spent + amount));
+        let backup = Address::generate(&env);
+        let expires_at = env.ledger().timestamp() + 86400;
+        assert!(client.try_delegate(&vault_id, &cfo, &ceo, &public_key(&env, &ceo), &expires_at, &10000).is_err());
+        client.delegate(&vault_id, &cfo, &backup, &public_key(&env, &backup), &expires_at, &10000);
         
-        // Update monthly spending
-        let monthly_spent: i128 = env.storage().instance()
-            .get(&DataKey::MonthlySpent(current_month))
-            .unwrap_or(0);
-        env.storage().instance().set(&DataKey::MonthlySpent(current_month), &(monthly_spent + amount));
+        // The delegate's vote counts as the CFO's single vote, with the delegation marker
+        let memo = Memo::Text(String::from_str(&env, "payout"));
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        assert!(!client.approve_as_delegate(&vault_id, &backup, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &backup)));
+        assert!(client.try_approve_transaction(&vault_id, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cfo)).is_err());
+        assert!(client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo)));
+        let approvals = client.get_transaction(&vault_id, &tx_id).unwrap().approvals;
+        assert_eq!(approvals.get(0).unwrap(), Approval { guardian: cfo.clone(), delegate: Some(backup.clone()) });
+        assert_eq!(approvals.get(1).unwrap(), Approval { guardian: ceo.clone(), delegate: None });
+        
+        // Out of scope: other guardians, larger amounts, after expiry or revocation
+        let large_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &20000, &memo, &TxType::Payment);
+        assert!(client.try_approve_as_delegate(&vault_id, &backup, &cfo, &large_id, &challenge_response(&env, &client, vault_id, large_id, &backup)).is_err());
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        let response = challenge_response(&env, &client, vault_id, tx_id, &backup);
+        assert!(client.try_approve_as_delegate(&vault_id, &backup, &ceo, &tx_id, &response).is_err());
+        env.ledger().with_mut(|ledger| ledger.timestamp = expires_at + 1);
+        assert!(client.try_approve_as_delegate(&vault_id, &backup, &cfo, &tx_id, &response).is_err());
+        client.delegate(&vault_id, &cfo, &backup, &public_key(&env, &backup), &(expires_at + 86400), &10000);
+        client.revoke_delegation(&vault_id, &cfo);
+        assert!(client.get_delegation(&vault_id, &cfo).is_none());
+        assert!(client.try_approve_as_delegate(&vault_id, &backup, &cfo, &tx_id, &response).is_err());
     }
-}
-
-// ==================== TESTS ====================
-
-#[cfg(test)]
-mod test {
-    use super::*;
-    use soroban_sdk::{testutils::Address as _, Env, Vec};
     
-    fn create_test_guardians(env: &Env) -> Vec<Guardian> {
-        let mut guardians = Vec::new(env);
-        guardians.push_back(Guardian {
-            address: Address::generate(env),
-            role: Symbol::new(env, "CEO"),
-            is_active: true,
-            daily_limit: 100000,
-            monthly_limit: 1000000,
-            approval_count: 0,
-            last_approval: 0,
-        });
-        guardians.push_back(Guardian {
-            address: Address::generate(env),
-            role: Symbol::new(env, "CFO"),
-            is_active: true,
-            daily_limit: 100000,
-            monthly_limit: 1000000,
-            approval_count: 0,
-            last_approval: 0,
-        });
-        guardians.push_back(Guardian {
-            address: Address::generate(env),
-            role: Symbol::new(env, "CTO"),
-            is_active: true,
-            daily_limit: 100000,
-            monthly_limit: 1000000,
-            approval_count: 0,
-            last_approval: 0,
+    #[test]
+    fn test_weighted_voting() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        let cto = guardians.get(2).unwrap().address;
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 50000);
+        
+        // CEO counts for 2, staff for 1; large amounts need weight 3
+        let mut policy = VotingPolicy {
+            weights: Vec::from_array(&env, [GuardianWeight { guardian: ceo.clone(), weight: 2 }]),
+            tiers: Vec::from_array(&env, [
+                WeightTier { min_amount: 0, required_weight: 2 },
+                WeightTier { min_amount: 10000, required_weight: 5 },
+            ]),
+        };
+        assert!(client.try_propose_voting_policy(&vault_id, &ceo, &policy).is_err());
+        policy.tiers.set(1, WeightTier { min_amount: 10000, required_weight: 3 });
+        let proposal_id = client.propose_voting_policy(&vault_id, &ceo, &policy);
+        assert!(!client.approve_proposal(&vault_id, &cfo, &proposal_id));
+        assert!(client.approve_proposal(&vault_id, &cto, &proposal_id));
+        assert_eq!(client.get_voting_policy(&vault_id), Some(policy));
+        
+        let memo = Memo::Text(String::from_str(&env, "payout"));
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        assert!(client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo)));
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().approval_weight, 2);
+        
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &20000, &memo, &TxType::Payment);
+        assert!(!client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo)));
+        assert!(client.approve_transaction(&vault_id, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cfo)));
+        let transaction = client.get_transaction(&vault_id, &tx_id).unwrap();
+        assert_eq!(transaction.approval_weight, 3);
+        assert_eq!(transaction.status, TxStatus::Executed);
+        
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &20000, &memo, &TxType::Payment);
+        assert!(!client.approve_transaction(&vault_id, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cfo)));
+        assert!(!client.approve_transaction(&vault_id, &cto, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cto)));
+        
+        // A wallet policy's approval count still applies on top of the weight tier
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().set(&(vault_id, DataKey::WalletPolicy(hot_wallet.clone())), &WalletPolicy {
+                daily_limit: 100000,
+                monthly_limit: 1000000,
+                high_value_threshold: 1000,
+                required_approvals: 2,
+            });
         });
-        guardians
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &5000, &memo, &TxType::Payment);
+        assert!(!client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo)));
+        assert!(client.approve_transaction(&vault_id, &cto, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cto)));
     }
     
-    fn create_test_system_limits() -> SystemLimits {
-        SystemLimits {
-            daily_limit: 100000,
-            monthly_limit: 1000000,
-            high_value_threshold: 1000,
-            required_approvals: 2,
-            cold_wallet_percentage: 95,
-            hot_wallet_percentage: 5,
-        }
+    #[test]
+    fn test_destination_velocity_limits() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 50000);
+        
+        let default_limit = DestinationLimit {
+            daily_amount: 1000,
+            monthly_amount: 5000,
+            daily_count: 2,
+            monthly_count: 10,
+        };
+        let vendor = Address::generate(&env);
+        let vendor_limit = DestinationLimit { daily_amount: 3000, ..default_limit.clone() };
+        let proposal_id = client.propose_destination_limit(&vault_id, &ceo, &None, &Some(default_limit.clone()));
+        client.approve_proposal(&vault_id, &cfo, &proposal_id);
+        let proposal_id = client.propose_destination_limit(&vault_id, &ceo, &Some(vendor.clone()), &Some(vendor_limit.clone()));
+        client.approve_proposal(&vault_id, &cfo, &proposal_id);
+        
+        // Caps apply per destination, in amount and in transaction count
+        let memo = Memo::Text(String::from_str(&env, "payout"));
+        let attacker = Address::generate(&env);
+        assert_eq!(client.get_destination_limit(&vault_id, &attacker), Some(default_limit));
+        client.create_transaction(&vault_id, &hot_wallet, &attacker, &600, &memo, &TxType::Payment);
+        assert!(client.try_create_transaction(&vault_id, &hot_wallet, &attacker, &500, &memo, &TxType::Payment).is_err());
+        client.create_transaction(&vault_id, &hot_wallet, &attacker, &300, &memo, &TxType::Payment);
+        assert!(client.try_create_transaction(&vault_id, &hot_wallet, &attacker, &10, &memo, &TxType::Payment).is_err());
+        let today = env.ledger().timestamp() / 86400;
+        assert_eq!(client.get_destination_usage(&vault_id, &attacker, &PeriodKey::Day(today)), DestinationUsage { amount: 900, count: 2 });
+        
+        // Batch legs count towards each leg's destination once executed
+        let legs = Vec::from_array(&env, [(vendor.clone(), 2000i128, memo.clone()), (vendor.clone(), 900i128, memo.clone())]);
+        let batch_id = client.create_batch_transaction(&vault_id, &hot_wallet, &legs);
+        assert_eq!(client.get_destination_usage(&vault_id, &vendor, &PeriodKey::Day(today)), DestinationUsage { amount: 0, count: 0 });
+        client.approve_transaction(&vault_id, &ceo, &batch_id, &challenge_response(&env, &client, vault_id, batch_id, &ceo));
+        client.approve_transaction(&vault_id, &cfo, &batch_id, &challenge_response(&env, &client, vault_id, batch_id, &cfo));
+        assert_eq!(client.get_destination_usage(&vault_id, &vendor, &PeriodKey::Day(today)), DestinationUsage { amount: 2900, count: 2 });
+        assert!(client.try_create_transaction(&vault_id, &hot_wallet, &vendor, &200, &memo, &TxType::Payment).is_err());
+        
+        // Daily caps reset the next day
+        env.ledger().with_mut(|ledger| ledger.timestamp += 86400);
+        client.create_transaction(&vault_id, &hot_wallet, &attacker, &500, &memo, &TxType::Payment);
+        
+        // Queued payments are checked again at execution, together they cannot exceed the cap
+        let first = client.create_transaction(&vault_id, &hot_wallet, &vendor, &2000, &memo, &TxType::Payment);
+        let second = client.create_transaction(&vault_id, &hot_wallet, &vendor, &2000, &memo, &TxType::Payment);
+        client.approve_transaction(&vault_id, &ceo, &first, &challenge_response(&env, &client, vault_id, first, &ceo));
+        client.approve_transaction(&vault_id, &cfo, &first, &challenge_response(&env, &client, vault_id, first, &cfo));
+        client.approve_transaction(&vault_id, &ceo, &second, &challenge_response(&env, &client, vault_id, second, &ceo));
+        assert!(client.try_approve_transaction(&vault_id, &cfo, &second, &challenge_response(&env, &client, vault_id, second, &cfo)).is_err());
+        assert_eq!(client.get_transaction(&vault_id, &second).unwrap().status, TxStatus::AwaitingApproval);
+        assert_eq!(client.get_destination_usage(&vault_id, &vendor, &PeriodKey::Day(today + 1)), DestinationUsage { amount: 2000, count: 1 });
+        
+        // Removing the default cap leaves other destinations unlimited
+        let proposal_id = client.propose_destination_limit(&vault_id, &ceo, &None, &None);
+        client.approve_proposal(&vault_id, &cfo, &proposal_id);
+        assert!(client.get_destination_limit(&vault_id, &attacker).is_none());
+        assert_eq!(client.get_destination_limit(&vault_id, &vendor), Some(vendor_limit));
+        client.create_transaction(&vault_id, &hot_wallet, &attacker, &900, &memo, &TxType::Payment);
     }
     
     #[test]
-    fn test_initialize_contract() {
+    fn test_reference_currency_limits() {
         let env = Env::default();
-        let contract_id = env.register(CustodyContract, ());
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, _) = setup_contract(&env);
         let client = CustodyContractClient::new(&env, &contract_id);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        let cto = guardians.get(2).unwrap().address;
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 100000);
         
-        let guardians = create_test_guardians(&env);
-        let hot_wallet = Address::generate(&env);
-        let cold_wallet = Address::generate(&env);
-        let system_limits = create_test_system_limits();
+        // One unit of the vault asset is worth 2 USD
+        let oracle_id = env.register(MockOracle, ());
+        let oracle = MockOracleClient::new(&env, &oracle_id);
+        oracle.set_price(&(2 * 10i128.pow(14)), &env.ledger().timestamp());
         
-        client.initialize(
-            &guardians,
-            &hot_wallet,
-            &cold_wallet,
-            &system_limits,
-        );
+        // Switching to USD limits needs every guardian
+        let pricing = ReferencePricing {
+            oracle: OracleConfig {
+                oracle: oracle_id.clone(),
+                asset: Asset::Other(Symbol::new(&env, "XLM")),
+                max_age: 3600,
+            },
+            limits: create_test_system_limits(),
+        };
+        let proposal_id = client.propose_reference_pricing(&vault_id, &ceo, &pricing);
+        assert!(!client.approve_proposal(&vault_id, &cfo, &proposal_id));
+        assert!(client.approve_proposal(&vault_id, &cto, &proposal_id));
+        assert_eq!(client.get_oracle_config(&vault_id), Some(pricing.oracle));
         
-        // Test that contract is initialized
-        let first_guardian = guardians.get(0).unwrap();
-        let guardian_count = client.get_guardian(&first_guardian.address);
-        assert!(guardian_count.is_some());
+        // 400 units = 800 USD is below the 1000 USD threshold, 600 units is above it
+        let memo = Memo::Text(String::from_str(&env, "payout"));
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &400, &memo, &TxType::Payment);
+        let transaction = client.get_transaction(&vault_id, &tx_id).unwrap();
+        assert_eq!(transaction.value, Some(800));
+        assert_eq!(transaction.status, TxStatus::Executed);
+        let usage = client.get_destination_usage(&vault_id, &transaction.to_address, &PeriodKey::Day(0));
+        assert_eq!(usage, DestinationUsage { amount: 800, count: 1 });
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &600, &memo, &TxType::Payment);
+        assert!(client.get_transaction(&vault_id, &tx_id).unwrap().requires_approval);
+        assert!(client.try_create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &60000, &memo, &TxType::Payment).is_err());
+        
+        // A stale price requires the maximum approval tier, even for small amounts
+        env.ledger().with_mut(|ledger| ledger.timestamp += 3601);
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &10, &memo, &TxType::Payment);
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().value, None);
+        assert!(!client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo)));
+        assert!(!client.approve_transaction(&vault_id, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cfo)));
+        assert!(client.approve_transaction(&vault_id, &cto, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cto)));
     }
     
     #[test]
-    fn test_create_transaction() {
+    fn test_custody_fees() {
         let env = Env::default();
-        let contract_id = env.register(CustodyContract, ());
+        env.mock_all_auths();
+        let (contract_id, vault_id, guardians, hot_wallet, cold_wallet) = setup_contract(&env);
         let client = CustodyContractClient::new(&env, &contract_id);
+        let ceo = guardians.get(0).unwrap().address;
+        let cfo = guardians.get(1).unwrap().address;
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
+        fund_wallet(&env, &contract_id, vault_id, &cold_wallet, WalletType::Cold, 100000);
         
-        let guardians = create_test_guardians(&env);
-        let hot_wallet = Address::generate(&env);
-        let cold_wallet = Address::generate(&env);
-        let system_limits = create_test_system_limits();
+        let fee_wallet = Address::generate(&env);
+        let fee_schedule = FeeSchedule {
+            fee_wallet: fee_wallet.clone(),
+            flat_fee: 10,
+            fee_bps: 50,
+            aum_fee_bps: 100,
+        };
+        let proposal_id = client.propose_fee_schedule(&vault_id, &ceo, &fee_schedule);
+        assert!(client.approve_proposal(&vault_id, &cfo, &proposal_id));
+        assert_eq!(client.get_fee_schedule(&vault_id), Some(fee_schedule));
+        assert_eq!(client.get_wallet_info(&vault_id, &fee_wallet).unwrap().info.wallet_type, WalletType::Fee);
         
-        // Initialize
-        client.initialize(
-            &guardians,
-            &hot_wallet,
-            &cold_wallet,
-            &system_limits,
-        );
+        // 10 flat + 0.5% of 400 is charged on top of the payment
+        let memo = Memo::Text(String::from_str(&env, "payout"));
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &400, &memo, &TxType::Payment);
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().fee, 12);
+        assert_eq!(client.get_wallet_balance(&vault_id, &hot_wallet), Some(10000 - 400 - 12));
+        assert_eq!(client.get_wallet_balance(&vault_id, &fee_wallet), Some(12));
         
-        // Add some balance to hot wallet for testing (simulate funding)
-        let hot_wallet_key = DataKey::WalletInfo(hot_wallet.clone());
-        let hot_wallet_info = WalletInfo {
-            address: hot_wallet.clone(),
-            wallet_type: WalletType::Hot,
-            balance: 10000, // Add sufficient balance
-            reserved_balance: 0,
-            is_active: true,
-        };
-        env.as_contract(&contract_id, || {
-            env.storage().instance().set(&hot_wallet_key, &hot_wallet_info);
-        });
+        // The fee is reserved with the amount, a payment of the whole balance is refused up front
+        let available = client.get_wallet_info(&vault_id, &hot_wallet).unwrap().available_balance;
+        assert!(client.try_create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &available, &memo, &TxType::Payment).is_err());
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &Address::generate(&env), &2000, &memo, &TxType::Payment);
+        assert_eq!(client.get_wallet_info(&vault_id, &hot_wallet).unwrap().info.reserved_balance, 2000 + 20);
+        assert!(!client.approve_transaction(&vault_id, &ceo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &ceo)));
+        assert!(client.approve_transaction(&vault_id, &cfo, &tx_id, &challenge_response(&env, &client, vault_id, tx_id, &cfo)));
+        assert_eq!(client.get_wallet_info(&vault_id, &hot_wallet).unwrap().info.reserved_balance, 0);
+        assert_eq!(client.get_wallet_balance(&vault_id, &fee_wallet), Some(12 + 20));
         
-        // Create transaction
-        let to_address = Address::generate(&env);
-        let amount = 500i128; // Below threshold
-        let memo = Symbol::new(&env, "test_tx");
+        // Rebalances between vault wallets are exempt
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &cold_wallet, &100, &memo, &TxType::Rebalance);
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().fee, 0);
         
-        let tx_id = client.create_transaction(
-            &hot_wallet,
-            &to_address,
-            &amount,
-            &memo,
-            &TxType::Payment,
-        );
+        // A year of 1% AUM fee on the hot and cold balances
+        env.ledger().with_mut(|ledger| ledger.timestamp += 365 * 86400);
+        assert_eq!(client.collect_aum_fee(&vault_id), 74 + 1000);
+        assert_eq!(client.collect_aum_fee(&vault_id), 0);
+        assert_eq!(client.get_wallet_balance(&vault_id, &cold_wallet), Some(100000 - 1000));
         
-        assert_eq!(tx_id, 1);
+        let today = env.ledger().timestamp() / 86400;
+        assert_eq!(client.get_fees(&vault_id, &PeriodKey::Day(today)), FeeLedgerEntry { transaction_fees: 0, aum_fees: 1074 });
+        assert_eq!(client.get_fees(&vault_id, &PeriodKey::Day(0)).transaction_fees, 12 + 20);
+        assert_eq!(client.get_wallet_balance(&vault_id, &fee_wallet), Some(12 + 20 + 1074));
         
-        // Check transaction exists
-        let transaction = client.get_transaction(&tx_id);
-        assert!(transaction.is_some());
+        // A vault wallet cannot collect the fees
+        let mut schedule = client.get_fee_schedule(&vault_id).unwrap();
+        schedule.fee_wallet = cold_wallet.clone();
+        assert!(client.try_propose_fee_schedule(&vault_id, &ceo, &schedule).is_err());
     }
     
     #[test]
-    fn test_system_queries() {
+    fn test_idempotent_transaction() {
         let env = Env::default();
-        let contract_id = env.register(CustodyContract, ());
+        env.mock_all_auths();
+        let (contract_id, vault_id, _, hot_wallet, _) = setup_contract(&env);
         let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
         
-        let guardians = create_test_guardians(&env);
-        let hot_wallet = Address::generate(&env);
-        let cold_wallet = Address::generate(&env);
-        let system_limits = create_test_system_limits();
+        let key = BytesN::from_array(&env, &[9; 32]);
+        let request = PaymentRequest {
+            from_wallet: hot_wallet.clone(),
+            to_address: Address::generate(&env),
+            amount: 500,
+            memo: Memo::Text(String::from_str(&env, "payout")),
+            tx_type: TxType::Payment,
+        };
+        let tx_id = client.create_transaction_idempotent(&vault_id, &key, &request);
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().idempotency_key, Some(key.clone()));
         
-        // Initialize
-        client.initialize(
-            &guardians,
-            &hot_wallet,
-            &cold_wallet,
-            &system_limits,
-        );
+        // A retry returns the same transaction and pays only once, and is authorized like the first call
+        assert_eq!(client.create_transaction_idempotent(&vault_id, &key, &request), tx_id);
+        assert_eq!(env.auths()[0].0, hot_wallet);
+        assert_eq!(client.get_wallet_balance(&vault_id, &hot_wallet), Some(9500));
         
-        // Test query functions
-        assert_eq!(client.get_transaction_counter(), 0);
-        assert!(!client.is_emergency_mode());
-        assert_eq!(client.get_hot_balance(), 0);
-        assert_eq!(client.get_cold_balance(), 0);
+        // The same key with different parameters is rejected
+        let mut changed = request.clone();
+        changed.amount = 600;
+        assert!(client.try_create_transaction_idempotent(&vault_id, &key, &changed).is_err());
+        assert_ne!(client.create_transaction_idempotent(&vault_id, &BytesN::from_array(&env, &[8; 32]), &changed), tx_id);
+    }
+    
+    #[test]
+    fn test_memo_in_execution_event() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, _, hot_wallet, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        fund_wallet(&env, &contract_id, vault_id, &hot_wallet, WalletType::Hot, 10000);
         
-        let limits = client.get_system_limits();
-        assert_eq!(limits.required_approvals, 2);
+        // Exchange deposit tagged with a numeric memo id
+        let exchange = Address::generate(&env);
+        let tx_id = client.create_transaction(&vault_id, &hot_wallet, &exchange, &500, &Memo::Id(1234567890123), &TxType::Withdrawal);
+        let events = env.events().all();
+        assert_eq!(events.len(), 1);
+        let (_, topics, data) = events.get(0).unwrap();
+        let expected_topics: Vec<soroban_sdk::Val> = (Symbol::new(&env, "transaction_executed"), vault_id).into_val(&env);
+        assert_eq!(topics, expected_topics);
+        let data: Map<Symbol, soroban_sdk::Val> = data.into_val(&env);
+        let memo: Memo = data.get(Symbol::new(&env, "memo")).unwrap().into_val(&env);
+        assert_eq!(memo, Memo::Id(1234567890123));
+        assert_eq!(client.get_transaction(&vault_id, &tx_id).unwrap().memo, Memo::Id(1234567890123));
+        
+        // Text memos follow the Stellar 28 byte limit
+        let too_long = Memo::Text(String::from_str(&env, "this memo is longer than 28 bytes"));
+        assert!(client.try_create_transaction(&vault_id, &hot_wallet, &exchange, &500, &too_long, &TxType::Payment).is_err());
+    }
+    
+    #[test]
+    fn test_storage_ttl_extended() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (contract_id, vault_id, _, _, _) = setup_contract(&env);
+        let client = CustodyContractClient::new(&env, &contract_id);
+        let guardians_ttl = || env.as_contract(&contract_id, || env.storage().persistent().get_ttl(&(vault_id, DataKey::Guardians)));
+        assert_eq!(guardians_ttl(), TTL_EXTEND_TO);
+        
+        // Reads extend entries again once they fall below the threshold
+        env.ledger().with_mut(|ledger| ledger.sequence_number += 100 * DAY_IN_LEDGERS);
+        assert_eq!(guardians_ttl(), TTL_EXTEND_TO - 100 * DAY_IN_LEDGERS);
+        assert_eq!(client.get_config(&vault_id).active_guardian_count, 3);
+        assert_eq!(guardians_ttl(), TTL_EXTEND_TO);
+        assert_eq!(env.as_contract(&contract_id, || env.storage().instance().get_ttl()), TTL_EXTEND_TO);
     }
-}
\ No newline at end of file
+}
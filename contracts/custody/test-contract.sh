@@ -11,12 +11,33 @@ fi
 
 # Load contract configuration
 CONTRACT_ADDRESS=$(jq -r '.contract_address' contract-config-testnet.json)
+VAULT_ID=$(jq -r '.vault_id' contract-config-testnet.json)
 GUARDIAN_CEO=$(jq -r '.guardians.ceo' contract-config-testnet.json)
 GUARDIAN_CFO=$(jq -r '.guardians.cfo' contract-config-testnet.json)
 GUARDIAN_CTO=$(jq -r '.guardians.cto' contract-config-testnet.json)
 HOT_WALLET=$(jq -r '.wallets.hot' contract-config-testnet.json)
 COLD_WALLET=$(jq -r '.wallets.cold' contract-config-testnet.json)
 
+# Guardian HSM keys (ed25519 PEM) used to sign approval challenges
+GUARDIAN_CEO_KEY=${GUARDIAN_CEO_KEY:-guardian_ceo.pem}
+GUARDIAN_CFO_KEY=${GUARDIAN_CFO_KEY:-guardian_cfo.pem}
+
+# Raw 32-byte ed25519 public key of a PEM key, hex encoded
+public_key_hex() {
+    openssl pkey -in "$1" -pubout -outform DER | tail -c 32 | xxd -p -c 32
+}
+
+# Sign "stellar-custody:challenge" || challenge of a transaction, hex encoded
+challenge_response() {
+    local key_file=$1
+    local tx_id=$2
+    local challenge
+    challenge=$(stellar contract invoke --id "$CONTRACT_ADDRESS" --source deployer --network testnet -- get_challenge --vault_id "$VAULT_ID" --tx_id "$tx_id" 2>/dev/null | tr -d '"')
+    { printf 'stellar-custody:challenge'; echo -n "$challenge" | xxd -r -p; } > challenge.bin
+    openssl pkeyutl -sign -rawin -inkey "$key_file" -in challenge.bin | xxd -p -c 64
+    rm -f challenge.bin
+}
+
 echo "📋 Testing Contract: $CONTRACT_ADDRESS"
 echo "👥 Guardians: CEO, CFO, CTO"
 echo "💰 Wallets: Hot, Cold"
@@ -24,8 +45,8 @@ echo ""
 
 # Test 1: Check initial balances
 echo "🔍 Test 1: Checking initial wallet balances..."
-HOT_BALANCE=$(stellar contract invoke --id "$CONTRACT_ADDRESS" --source deployer --network testnet -- get_hot_balance 2>/dev/null || echo "0")
-COLD_BALANCE=$(stellar contract invoke --id "$CONTRACT_ADDRESS" --source deployer --network testnet -- get_cold_balance 2>/dev/null || echo "0")
+HOT_BALANCE=$(stellar contract invoke --id "$CONTRACT_ADDRESS" --source deployer --network testnet -- get_hot_balance --vault_id "$VAULT_ID" 2>/dev/null || echo "0")
+COLD_BALANCE=$(stellar contract invoke --id "$CONTRACT_ADDRESS" --source deployer --network testnet -- get_cold_balance --vault_id "$VAULT_ID" 2>/dev/null || echo "0")
 
 echo "   Hot Balance: $HOT_BALANCE XLM"
 echo "   Cold Balance: $COLD_BALANCE XLM"
@@ -33,7 +54,7 @@ echo "   Cold Balance: $COLD_BALANCE XLM"
 # Test 2: Check guardian information
 echo ""
 echo "🔍 Test 2: Checking guardian information..."
-CEO_INFO=$(stellar contract invoke --id "$CONTRACT_ADDRESS" --source deployer --network testnet -- get_guardian --guardian_address "$GUARDIAN_CEO" 2>/dev/null || echo "null")
+CEO_INFO=$(stellar contract invoke --id "$CONTRACT_ADDRESS" --source deployer --network testnet -- get_guardian --vault_id "$VAULT_ID" --guardian_address "$GUARDIAN_CEO" 2>/dev/null || echo "null")
 echo "   CEO Guardian: $(echo $CEO_INFO | jq -r '.role // "Not found"' 2>/dev/null || echo "Not found")"
 
 # Test 3: Create a small transaction (should execute automatically)
@@ -51,10 +72,11 @@ SMALL_TX_RESULT=$(stellar contract invoke \
     --network testnet \
     -- \
     create_transaction \
+    --vault_id "$VAULT_ID" \
     --from_wallet "$HOT_WALLET" \
     --to_address "$RECIPIENT" \
     --amount 500 \
-    --memo "small_test" \
+    --memo '{"Text":"small_test"}' \
     --tx_type "Payment" \
     2>/dev/null || echo "failed")
 
@@ -73,10 +95,11 @@ HIGH_TX_RESULT=$(stellar contract invoke \
     --network testnet \
     -- \
     create_transaction \
+    --vault_id "$VAULT_ID" \
     --from_wallet "$COLD_WALLET" \
     --to_address "$RECIPIENT" \
     --amount 5000 \
-    --memo "high_value_test" \
+    --memo '{"Text":"high_value_test"}' \
     --tx_type "Payment" \
     2>/dev/null || echo "failed")
 
@@ -88,6 +111,27 @@ if [[ $HIGH_TX_RESULT != "failed" ]]; then
     echo ""
     echo "🔍 Test 5: Testing guardian approvals..."
     
+    # Challenge responses are signed with the guardians' registered HSM keys
+    for GUARDIAN in ceo cfo; do
+        if [[ $GUARDIAN == "ceo" ]]; then
+            GUARDIAN_ADDRESS=$GUARDIAN_CEO
+            KEY_FILE=$GUARDIAN_CEO_KEY
+        else
+            GUARDIAN_ADDRESS=$GUARDIAN_CFO
+            KEY_FILE=$GUARDIAN_CFO_KEY
+        fi
+        stellar contract invoke \
+            --id "$CONTRACT_ADDRESS" \
+            --source "guardian_$GUARDIAN" \
+            --network testnet \
+            -- \
+            register_guardian_key \
+            --vault_id "$VAULT_ID" \
+            --guardian "$GUARDIAN_ADDRESS" \
+            --public_key "$(public_key_hex "$KEY_FILE")" \
+            > /dev/null 2>&1
+    done
+    
     # First approval
     echo "   👤 CEO approving transaction..."
     CEO_APPROVAL=$(stellar contract invoke \
@@ -96,8 +140,10 @@ if [[ $HIGH_TX_RESULT != "failed" ]]; then
         --network testnet \
         -- \
         approve_transaction \
+        --vault_id "$VAULT_ID" \
         --guardian "$GUARDIAN_CEO" \
         --tx_id "$TX_ID" \
+        --response "$(challenge_response "$GUARDIAN_CEO_KEY" "$TX_ID")" \
         2>/dev/null || echo "failed")
     
     if [[ $CEO_APPROVAL != "failed" ]]; then
@@ -114,8 +160,10 @@ if [[ $HIGH_TX_RESULT != "failed" ]]; then
         --network testnet \
         -- \
         approve_transaction \
+        --vault_id "$VAULT_ID" \
         --guardian "$GUARDIAN_CFO" \
         --tx_id "$TX_ID" \
+        --response "$(challenge_response "$GUARDIAN_CFO_KEY" "$TX_ID")" \
         2>/dev/null || echo "failed")
     
     if [[ $CFO_APPROVAL != "failed" ]]; then
@@ -132,6 +180,7 @@ if [[ $HIGH_TX_RESULT != "failed" ]]; then
         --network testnet \
         -- \
         get_transaction \
+        --vault_id "$VAULT_ID" \
         --tx_id "$TX_ID" \
         2>/dev/null || echo "null")
     
@@ -150,6 +199,7 @@ EMERGENCY_RESULT=$(stellar contract invoke \
     --network testnet \
     -- \
     emergency_shutdown \
+    --vault_id "$VAULT_ID" \
     --guardian "$GUARDIAN_CEO" \
     2>/dev/null || echo "failed")
 
@@ -164,10 +214,11 @@ if [[ $EMERGENCY_RESULT != "failed" ]]; then
         --network testnet \
         -- \
         create_transaction \
+        --vault_id "$VAULT_ID" \
         --from_wallet "$HOT_WALLET" \
         --to_address "$RECIPIENT" \
         --amount 100 \
-        --memo "emergency_test" \
+        --memo '{"Text":"emergency_test"}' \
         --tx_type "Payment" \
         2>&1 || echo "blocked")
     
@@ -185,6 +236,7 @@ if [[ $EMERGENCY_RESULT != "failed" ]]; then
         --network testnet \
         -- \
         disable_emergency_mode \
+        --vault_id "$VAULT_ID" \
         --guardians_approval "[$GUARDIAN_CEO,$GUARDIAN_CFO]" \
         2>/dev/null || echo "failed")
     
@@ -207,6 +259,7 @@ REBALANCE_RESULT=$(stellar contract invoke \
     --network testnet \
     -- \
     rebalance_wallets \
+    --vault_id "$VAULT_ID" \
     --guardian "$GUARDIAN_CEO" \
     2>/dev/null || echo "failed")
 
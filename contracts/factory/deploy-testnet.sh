@@ -0,0 +1,65 @@
+#!/bin/bash
+
+# Stellar Custody Vault Factory Testnet Deployment
+echo "🏭 Deploying Stellar Custody Vault Factory to Testnet..."
+
+# Configuration
+NETWORK="testnet"
+CUSTODY_WASM="../../target/wasm32v1-none/release/stellar_custody_contract.wasm"
+FACTORY_WASM="../../target/wasm32v1-none/release/stellar_custody_factory.wasm"
+
+# The factory embeds the custody contract interface, the workspace Makefile builds custody first
+echo "🔨 Building custody and factory contracts..."
+make -C ../.. build || exit 1
+
+# Upload the custody WASM so the factory can deploy instances of it
+echo "📤 Uploading custody WASM..."
+WASM_HASH=$(stellar contract upload \
+    --wasm "$CUSTODY_WASM" \
+    --source deployer \
+    --network "$NETWORK" \
+    --network-passphrase "Test SDF Network ; September 2015" \
+    2>&1)
+
+if [[ ! $WASM_HASH =~ ^[0-9a-f]{64}$ ]]; then
+    echo "❌ Upload failed!"
+    echo "Error: $WASM_HASH"
+    exit 1
+fi
+
+# Deploy the factory, setting admin and WASM hash in its constructor
+ADMIN=$(stellar keys address deployer)
+FACTORY_ADDRESS=$(stellar contract deploy \
+    --wasm "$FACTORY_WASM" \
+    --source deployer \
+    --network "$NETWORK" \
+    --network-passphrase "Test SDF Network ; September 2015" \
+    -- \
+    --admin "$ADMIN" \
+    --wasm_hash "$WASM_HASH" \
+    2>&1)
+
+if [[ $FACTORY_ADDRESS == C* ]]; then
+    echo "✅ Factory deployed successfully!"
+    echo "📋 Factory Address: $FACTORY_ADDRESS"
+    
+    cat > factory-config-testnet.json << JSON
+{
+    "factory_address": "$FACTORY_ADDRESS",
+    "custody_wasm_hash": "$WASM_HASH",
+    "admin": "$ADMIN",
+    "network": "testnet",
+    "deployed_at": "$(date -u +"%Y-%m-%dT%H:%M:%SZ")"
+}
+JSON
+    echo "💾 Configuration saved to factory-config-testnet.json"
+    
+    echo ""
+    echo "🎯 Next steps:"
+    echo "1. Deploy a vault: stellar contract invoke --id $FACTORY_ADDRESS --source owner --network testnet -- deploy_vault ..."
+    echo "2. List vaults: stellar contract invoke --id $FACTORY_ADDRESS --source owner --network testnet -- list_vaults --owner <OWNER>"
+else
+    echo "❌ Deployment failed!"
+    echo "Error: $FACTORY_ADDRESS"
+    exit 1
+fi
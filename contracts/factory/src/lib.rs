@@ -0,0 +1,283 @@
+#![no_std]
+
+use soroban_sdk::{
+    contract, contractimpl, contracttype, contractmeta, log, xdr::ToXdr,
+    Address, Bytes, BytesN, Env, Vec,
+};
+
+// Contract metadata
+contractmeta!(
+    key = "Description",
+    val = "Stellar Custody Vault Factory"
+);
+
+contractmeta!(
+    key = "Version",
+    val = "1.0.0"
+);
+
+/// Client and types of the custody contract, generated from its WASM
+/// (build it first with `make custody` at the workspace root)
+pub mod custody {
+    use soroban_sdk::auth::Context;
+    
+    soroban_sdk::contractimport!(
+        file = "../../target/wasm32v1-none/release/stellar_custody_contract.wasm"
+    );
+}
+
+use custody::{Guardian, SystemLimits};
+
+// ==================== DATA STRUCTURES ====================
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct VaultRecord {
+    pub address: Address,
+    pub owner: Address,
+    pub salt: BytesN<32>,
+    pub wasm_hash: BytesN<32>,
+    pub vault_id: u32,
+    pub created_at: u64,
+}
+
+// ==================== STORAGE KEYS ====================
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Admin,
+    CustodyWasmHash,
+    VaultCount,
+    Vault(Address),
+    VaultsByOwner(Address),
+}
+
+// ==================== MAIN CONTRACT ====================
+
+#[contract]
+pub struct CustodyFactory;
+
+#[contractimpl]
+impl CustodyFactory {
+    /// Set the admin and the custody WASM hash at deploy time, so the factory is never left uninitialized
+    pub fn __constructor(env: Env, admin: Address, wasm_hash: BytesN<32>) {
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::CustodyWasmHash, &wasm_hash);
+        env.storage().instance().set(&DataKey::VaultCount, &0u32);
+    }
+    
+    /// Replace the custody WASM used for new vaults (existing vaults are not affected)
+    pub fn set_custody_wasm(env: Env, admin: Address, wasm_hash: BytesN<32>) {
+        admin.require_auth();
+        
+        let stored_admin: Address = env.storage().instance().get(&DataKey::Admin).unwrap();
+        if admin != stored_admin {
+            panic!("Not the factory admin");
+        }
+        
+        env.storage().instance().set(&DataKey::CustodyWasmHash, &wasm_hash);
+        
+        log!(&env, "Custody WASM hash updated");
+    }
+    
+    /// Deploy a custody contract and create its primary vault in the same invocation
+    pub fn deploy_vault(
+        env: Env,
+        owner: Address,
+        salt: BytesN<32>,
+        guardians: Vec<Guardian>,
+        hot_wallet: Address,
+        cold_wallet: Address,
+        system_limits: SystemLimits,
+    ) -> Address {
+        owner.require_auth();
+        
+        let wasm_hash: BytesN<32> = env.storage().instance().get(&DataKey::CustodyWasmHash).unwrap();
+        let deployer = env.deployer().with_current_contract(Self::owner_salt(&env, &owner, &salt));
+        if env.storage().persistent().has(&DataKey::Vault(deployer.deployed_address())) {
+            panic!("Salt already used by this owner");
+        }
+        
+        // Deploy and initialize atomically: if vault creation fails the deployment is rolled back.
+        // The owner becomes the custody admin, the only one allowed to create vaults on it
+        let address = deployer.deploy_v2(wasm_hash.clone(), (owner.clone(),));
+        let vault_id = custody::Client::new(&env, &address).create_vault(
+            &guardians,
+            &hot_wallet,
+            &cold_wallet,
+            &system_limits,
+        );
+        
+        let record = VaultRecord {
+            address: address.clone(),
+            owner: owner.clone(),
+            salt,
+            wasm_hash,
+            vault_id,
+            created_at: env.ledger().timestamp(),
+        };
+        env.storage().persistent().set(&DataKey::Vault(address.clone()), &record);
+        
+        let mut owned = Self::list_vaults(env.clone(), owner.clone());
+        owned.push_back(address.clone());
+        env.storage().persistent().set(&DataKey::VaultsByOwner(owner), &owned);
+        
+        let count: u32 = env.storage().instance().get(&DataKey::VaultCount).unwrap_or(0);
+        env.storage().instance().set(&DataKey::VaultCount, &(count + 1));
+        
+        log!(&env, "Custody vault deployed");
+        address
+    }
+    
+    /// Predict the address `deploy_vault` will use for an owner and salt
+    pub fn get_vault_address(env: Env, owner: Address, salt: BytesN<32>) -> Address {
+        env.deployer()
+            .with_current_contract(Self::owner_salt(&env, &owner, &salt))
+            .deployed_address()
+    }
+    
+    /// Get the registry record of a deployed vault
+    pub fn get_vault(env: Env, address: Address) -> Option<VaultRecord> {
+        env.storage().persistent().get(&DataKey::Vault(address))
+    }
+    
+    /// List the vaults deployed for an owner, oldest first
+    pub fn list_vaults(env: Env, owner: Address) -> Vec<Address> {
+        env.storage().persistent()
+            .get(&DataKey::VaultsByOwner(owner))
+            .unwrap_or(Vec::new(&env))
+    }
+    
+    /// Get the total number of vaults deployed by this factory
+    pub fn get_vault_count(env: Env) -> u32 {
+        env.storage().instance().get(&DataKey::VaultCount).unwrap_or(0)
+    }
+    
+    /// Get the custody WASM hash used for new vaults
+    pub fn get_custody_wasm(env: Env) -> BytesN<32> {
+        env.storage().instance().get(&DataKey::CustodyWasmHash).unwrap()
+    }
+    
+    /// Get the factory admin
+    pub fn get_admin(env: Env) -> Address {
+        env.storage().instance().get(&DataKey::Admin).unwrap()
+    }
+    
+    // ==================== INTERNAL FUNCTIONS ====================
+    
+    /// Namespace salts by owner so nobody can claim another owner's vault address
+    fn owner_salt(env: &Env, owner: &Address, salt: &BytesN<32>) -> BytesN<32> {
+        let mut preimage: Bytes = owner.clone().to_xdr(env);
+        preimage.append(&salt.clone().into());
+        env.crypto().sha256(&preimage).to_bytes()
+    }
+}
+
+// ==================== TESTS ====================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use soroban_sdk::{testutils::Address as _, Env, Symbol, Vec};
+    
+    fn create_test_guardians(env: &Env) -> Vec<Guardian> {
+        let mut guardians = Vec::new(env);
+        for role in ["CEO", "CFO", "CTO"] {
+            guardians.push_back(Guardian {
+                address: Address::generate(env),
+                role: Symbol::new(env, role),
+                is_active: true,
+                daily_limit: 100000,
+                monthly_limit: 1000000,
+                approval_count: 0,
+                last_approval: 0,
+            });
+        }
+        guardians
+    }
+    
+    fn create_test_system_limits() -> SystemLimits {
+        SystemLimits {
+            daily_limit: 100000,
+            monthly_limit: 1000000,
+            high_value_threshold: 1000,
+            required_approvals: 2,
+            cold_wallet_percentage: 95,
+            hot_wallet_percentage: 5,
+        }
+    }
+    
+    fn setup_factory(env: &Env) -> (Address, Address) {
+        let admin = Address::generate(env);
+        let wasm_hash = env.deployer().upload_contract_wasm(custody::WASM);
+        let factory_id = env.register(CustodyFactory, (&admin, &wasm_hash));
+        (factory_id, admin)
+    }
+    
+    #[test]
+    fn test_deploy_vault() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (factory_id, _) = setup_factory(&env);
+        let client = CustodyFactoryClient::new(&env, &factory_id);
+        
+        let owner = Address::generate(&env);
+        let salt = BytesN::from_array(&env, &[7; 32]);
+        let guardians = create_test_guardians(&env);
+        let hot_wallet = Address::generate(&env);
+        let predicted = client.get_vault_address(&owner, &salt);
+        
+        let address = client.deploy_vault(
+            &owner,
+            &salt,
+            &guardians,
+            &hot_wallet,
+            &Address::generate(&env),
+            &create_test_system_limits(),
+        );
+        assert_eq!(address, predicted);
+        
+        // The deployed custody contract is already initialized with the owner's guardians
+        let vault = custody::Client::new(&env, &address);
+        let record = client.get_vault(&address).unwrap();
+        assert_eq!(record.owner, owner);
+        assert_eq!(vault.get_admin(), owner);
+        assert_eq!(vault.get_vault_count(), 1);
+        assert!(vault.get_guardian(&record.vault_id, &guardians.get(0).unwrap().address).is_some());
+        assert_eq!(vault.get_config(&record.vault_id).hot_wallet.info.address, hot_wallet);
+        
+        // Registry per owner, salts namespaced by owner and never reused
+        assert_eq!(client.list_vaults(&owner), Vec::from_array(&env, [address.clone()]));
+        assert!(client.try_deploy_vault(&owner, &salt, &guardians, &hot_wallet, &hot_wallet, &create_test_system_limits()).is_err());
+        let other = Address::generate(&env);
+        let other_address = client.deploy_vault(&other, &salt, &guardians, &hot_wallet, &hot_wallet, &create_test_system_limits());
+        assert_ne!(other_address, address);
+        assert_eq!(client.list_vaults(&other).len(), 1);
+        assert_eq!(client.get_vault_count(), 2);
+    }
+    
+    #[test]
+    fn test_failed_initialization_rolls_back() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (factory_id, admin) = setup_factory(&env);
+        let client = CustodyFactoryClient::new(&env, &factory_id);
+        
+        // Two guardians are rejected by the custody contract, so nothing is deployed or registered
+        let owner = Address::generate(&env);
+        let salt = BytesN::from_array(&env, &[1; 32]);
+        let mut guardians = create_test_guardians(&env);
+        guardians.pop_back();
+        let wallet = Address::generate(&env);
+        assert!(client.try_deploy_vault(&owner, &salt, &guardians, &wallet, &wallet, &create_test_system_limits()).is_err());
+        assert_eq!(client.list_vaults(&owner).len(), 0);
+        assert_eq!(client.get_vault_count(), 0);
+        
+        // Only the admin can change the custody WASM
+        let wasm_hash = client.get_custody_wasm();
+        assert!(client.try_set_custody_wasm(&owner, &wasm_hash).is_err());
+        client.set_custody_wasm(&admin, &wasm_hash);
+        assert_eq!(client.get_admin(), admin);
+    }
+}